@@ -0,0 +1,586 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::yaixm::{
+    Arc, Boundary, Circle, Distance, DistanceUnit, Feature, IcaoClass, IcaoType, LatLon, Level,
+    LocalType, Release, Rule, Volume, Yaixm, SCHEMA_VERSION,
+};
+use chrono::Utc;
+use geo::{Destination, Geodesic};
+use roxmltree::Node;
+
+// Import of AIXM 5.1 Airspace features. Only volumes with their own
+// horizontal projection are supported, derived (contributor) volumes and
+// references to GeoBorder features are skipped
+
+const FT_PER_M: f64 = 3.28084;
+
+// Child element with local name
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name).and_then(|n| n.text()).map(str::trim)
+}
+
+// Coordinate pairs, EPSG:4326 is lat/lon, CRS84 is lon/lat
+fn parse_positions(text: &str, lon_first: bool) -> Result<Vec<LatLon>, String> {
+    let values = text
+        .split_whitespace()
+        .map(|x| {
+            x.parse::<f64>()
+                .map_err(|_| format!("invalid position \"{}\"", x))
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+    if values.len() % 2 != 0 {
+        return Err("odd number of values in position list".to_string());
+    }
+
+    Ok(values
+        .chunks(2)
+        .map(|xy| {
+            if lon_first {
+                LatLon::from_degrees(xy[1], xy[0])
+            } else {
+                LatLon::from_degrees(xy[0], xy[1])
+            }
+        })
+        .collect())
+}
+
+// Positions from posList or pos elements, including those in pointProperty
+fn node_positions(node: Node, lon_first: bool) -> Result<Vec<LatLon>, String> {
+    if let Some(text) = child_text(node, "posList") {
+        return parse_positions(text, lon_first);
+    }
+
+    let mut positions = vec![];
+    for pos in node
+        .descendants()
+        .filter(|n| n.has_tag_name("pos"))
+        .filter_map(|n| n.text())
+    {
+        positions.extend(parse_positions(pos, lon_first)?);
+    }
+    Ok(positions)
+}
+
+fn parse_distance(node: Node) -> Result<Distance, String> {
+    let value = node
+        .text()
+        .and_then(|x| x.trim().parse::<f64>().ok())
+        .ok_or("invalid radius")?;
+    let unit = match node.attribute("uom").unwrap_or_default() {
+        "NM" | "[nmi_i]" => DistanceUnit::Nm,
+        "KM" | "km" => DistanceUnit::Km,
+        "M" | "m" => DistanceUnit::M,
+        "FT" | "[ft_i]" => DistanceUnit::Ft,
+        uom => return Err(format!("unknown radius unit \"{}\"", uom)),
+    };
+    Ok(Distance { value, unit })
+}
+
+fn destination(centre: &LatLon, bearing: f64, radius: &Distance) -> LatLon {
+    let point = Geodesic::destination(centre.point(), bearing, radius.metres());
+    LatLon::from_degrees(point.y(), point.x())
+}
+
+// Append points to boundary, extending previous line
+fn push_points(boundary: &mut Vec<Boundary>, points: Vec<LatLon>) {
+    if let Some(Boundary::Line(line)) = boundary.last_mut() {
+        for point in points {
+            if line.last() != Some(&point) {
+                line.push(point);
+            }
+        }
+    } else if !points.is_empty() {
+        boundary.push(Boundary::Line(points));
+    }
+}
+
+// Curve segment. Arc angles are bearings, clockwise from north, and arcs
+// are clockwise if the end angle is greater than the start angle. Arcs
+// through north use angles outside 0-360, e.g. 350 to 370 or 10 to -10
+fn parse_segment(
+    segment: Node,
+    boundary: &mut Vec<Boundary>,
+    lon_first: bool,
+) -> Result<(), String> {
+    match segment.tag_name().name() {
+        "GeodesicString" | "LineStringSegment" | "LineString" => {
+            push_points(boundary, node_positions(segment, lon_first)?);
+        }
+        "CircleByCenterPoint" => {
+            let centre = *node_positions(segment, lon_first)?
+                .first()
+                .ok_or("missing circle centre")?;
+            let radius = parse_distance(child(segment, "radius").ok_or("missing radius")?)?;
+            boundary.push(Boundary::Circle(Circle { centre, radius }));
+        }
+        "ArcByCenterPoint" => {
+            let centre = *node_positions(segment, lon_first)?
+                .first()
+                .ok_or("missing arc centre")?;
+            let radius = parse_distance(child(segment, "radius").ok_or("missing radius")?)?;
+            let angle = |name| {
+                child_text(segment, name)
+                    .and_then(|x| x.parse::<f64>().ok())
+                    .ok_or(format!("missing {}", name))
+            };
+            let (start, end) = (angle("startAngle")?, angle("endAngle")?);
+            let from = destination(&centre, start.rem_euclid(360.0), &radius);
+
+            // Equal angles are a full circle on their own, otherwise just
+            // a point on the ring
+            if start == end {
+                if boundary.is_empty() {
+                    boundary.push(Boundary::Circle(Circle { centre, radius }));
+                } else {
+                    push_points(boundary, vec![from]);
+                }
+                return Ok(());
+            }
+
+            // Arcs start from the end of the previous segment
+            push_points(boundary, vec![from]);
+            boundary.push(Boundary::Arc(Arc {
+                centre,
+                dir: if end > start { "cw" } else { "ccw" }.to_string(),
+                radius,
+                to: destination(&centre, end.rem_euclid(360.0), &radius),
+            }));
+        }
+        name => return Err(format!("unsupported segment \"{}\"", name)),
+    }
+    Ok(())
+}
+
+// Boundary of horizontal projection (first polygon patch exterior)
+fn parse_boundary(projection: Node) -> Result<Vec<Boundary>, String> {
+    let surface = projection
+        .children()
+        .find(|n| n.is_element())
+        .ok_or("empty horizontal projection")?;
+    let lon_first = surface
+        .attribute("srsName")
+        .is_some_and(|srs| srs.contains("CRS84"));
+
+    let exterior = surface
+        .descendants()
+        .find(|n| n.has_tag_name("exterior"))
+        .ok_or("missing polygon exterior")?;
+    let ring = exterior
+        .children()
+        .find(|n| n.is_element())
+        .ok_or("empty polygon exterior")?;
+
+    let mut boundary = vec![];
+    if ring.has_tag_name("LinearRing") {
+        push_points(&mut boundary, node_positions(ring, lon_first)?);
+    } else {
+        for curve in ring.descendants().filter(|n| n.has_tag_name("curveMember")) {
+            if curve.has_attribute(("http://www.w3.org/1999/xlink", "href")) {
+                return Err("curve references are not supported".to_string());
+            }
+            for segments in curve.descendants().filter(|n| n.has_tag_name("segments")) {
+                for segment in segments.children().filter(|n| n.is_element()) {
+                    parse_segment(segment, &mut boundary, lon_first)?;
+                }
+            }
+        }
+    }
+
+    if boundary.len() > 1 && boundary.iter().any(|b| matches!(b, Boundary::Circle(_))) {
+        return Err("circle in boundary with other segments".to_string());
+    }
+
+    // Closing point is implicit in YAIXM lines
+    let first = match boundary.first() {
+        Some(Boundary::Line(line)) => line.first().copied(),
+        _ => None,
+    };
+    if let Some(Boundary::Line(line)) = boundary.last_mut() {
+        if line.len() > 1 && line.last().copied() == first {
+            line.pop();
+        }
+    }
+    if boundary.is_empty() {
+        return Err("empty boundary".to_string());
+    }
+    Ok(boundary)
+}
+
+// Upper or lower limit, with reference (SFC, MSL or STD)
+fn parse_level(volume: Node, limit: &str) -> Result<Level, String> {
+    let node = child(volume, limit).ok_or(format!("missing {}", limit))?;
+    let value = node.text().unwrap_or_default().trim();
+    let reference = child_text(volume, &format!("{}Reference", limit)).unwrap_or("MSL");
+
+    match value {
+        "GND" | "SFC" => return Ok(Level::Sfc),
+        "UNL" => return Ok(Level::Unlimited),
+        _ => (),
+    }
+
+    let x = value
+        .parse::<f64>()
+        .map_err(|_| format!("invalid {} \"{}\"", limit, value))?;
+    let feet = match node.attribute("uom").unwrap_or("FT") {
+        "FL" => return Ok(Level::Fl(x.round() as u16)),
+        "FT" | "[ft_i]" => x,
+        "M" | "m" => x * FT_PER_M,
+        uom => return Err(format!("unknown {} unit \"{}\"", limit, uom)),
+    };
+    let feet = feet.round() as u32;
+
+    Ok(match reference {
+        "STD" => Level::Fl((feet / 100) as u16),
+        "SFC" if feet == 0 => Level::Sfc,
+        "SFC" => Level::Height(feet),
+        _ => Level::Altitude(feet),
+    })
+}
+
+fn parse_class(text: &str) -> Option<IcaoClass> {
+    match text {
+        "A" => Some(IcaoClass::A),
+        "B" => Some(IcaoClass::B),
+        "C" => Some(IcaoClass::C),
+        "D" => Some(IcaoClass::D),
+        "E" => Some(IcaoClass::E),
+        "F" => Some(IcaoClass::F),
+        "G" => Some(IcaoClass::G),
+        _ => None,
+    }
+}
+
+// ICAO type, local type and rules from AIXM airspace type
+fn parse_type(text: &str) -> (IcaoType, Option<LocalType>, Option<Vec<Rule>>) {
+    match text {
+        "ATZ" | "ATZ_P" => (IcaoType::Atz, None, None),
+        "AWY" => (IcaoType::Awy, None, None),
+        "CTA" | "CTA_P" => (IcaoType::Cta, None, None),
+        "CTR" | "CTR_P" => (IcaoType::Ctr, None, None),
+        "D" => (IcaoType::D, None, None),
+        "D_OTHER" => (IcaoType::DOther, None, None),
+        "P" => (IcaoType::P, None, None),
+        "R" => (IcaoType::R, None, None),
+        "TMA" | "TMA_P" => (IcaoType::Tma, None, None),
+        "TRA" | "TSA" => (IcaoType::D, None, Some(vec![Rule::Tra])),
+        "RMZ" => (IcaoType::Other, Some(LocalType::Rmz), None),
+        "TMZ" => (IcaoType::Other, Some(LocalType::Tmz), None),
+        _ => (IcaoType::Other, None, None),
+    }
+}
+
+fn parse_volume(volume: Node, class: Option<IcaoClass>) -> Result<Volume, String> {
+    let projection = child(volume, "horizontalProjection").ok_or("missing projection")?;
+
+    Ok(Volume {
+        id: volume
+            .attribute(("http://www.opengis.net/gml/3.2", "id"))
+            .map(str::to_string),
+        name: None,
+        lower: parse_level(volume, "lowerLimit")?,
+        upper: parse_level(volume, "upperLimit")?,
+        icao_class: class,
+        rules: None,
+        seq: None,
+        frequency: None,
+        callsign: None,
+        airtype: None,
+        boundary: parse_boundary(projection)?,
+    })
+}
+
+// Airspace feature from first time slice, None if no supported volumes
+fn parse_airspace(airspace: Node) -> Result<Option<Feature>, String> {
+    let Some(slice) = airspace
+        .descendants()
+        .find(|n| n.has_tag_name("AirspaceTimeSlice"))
+    else {
+        return Ok(None);
+    };
+
+    let designator = child_text(slice, "designator");
+    let name = child_text(slice, "name")
+        .or(designator)
+        .unwrap_or("UNNAMED")
+        .to_string();
+    let aixm_type = child_text(slice, "type").unwrap_or_default();
+
+    // FIR type boundaries would cover everything else
+    if matches!(aixm_type, "FIR" | "FIR_P" | "UIR" | "UIR_P") {
+        return Ok(None);
+    }
+    let (icao_type, local_type, rules) = parse_type(aixm_type);
+
+    let class = slice
+        .descendants()
+        .find(|n| n.has_tag_name("classification"))
+        .and_then(|n| n.text())
+        .and_then(|x| parse_class(x.trim()));
+
+    let mut geometry = vec![];
+    for volume in slice
+        .descendants()
+        .filter(|n| n.has_tag_name("AirspaceVolume"))
+        .filter(|n| child(*n, "horizontalProjection").is_some())
+    {
+        geometry.push(parse_volume(volume, class).map_err(|e| format!("{}: {}", name, e))?);
+    }
+    if geometry.is_empty() {
+        return Ok(None);
+    }
+
+    let id = child(airspace, "identifier")
+        .and_then(|n| n.text())
+        .map(str::trim)
+        .or(designator)
+        .map(str::to_string);
+
+    Ok(Some(Feature {
+        id,
+        name,
+        icao_type,
+        local_type,
+        icao_class: class,
+        rules,
+        geometry,
+    }))
+}
+
+// Parse AIXM 5.1 XML, e.g. an eAIP dataset
+pub fn from_aixm(text: &str) -> Result<Yaixm, String> {
+    let doc = roxmltree::Document::parse(text).map_err(|e| e.to_string())?;
+
+    let mut airspace = vec![];
+    for node in doc.descendants().filter(|n| n.has_tag_name("Airspace")) {
+        if let Some(feature) = parse_airspace(node)? {
+            airspace.push(feature);
+        }
+    }
+    if airspace.is_empty() {
+        return Err("no airspace found in AIXM data".to_string());
+    }
+
+    // Effective date from first time slice
+    let now = Utc::now().to_rfc3339();
+    let airac_date = doc
+        .descendants()
+        .find(|n| n.has_tag_name("beginPosition"))
+        .and_then(|n| n.text())
+        .map(str::trim)
+        .filter(|x| x.len() >= 10)
+        .map_or(now.clone(), str::to_string);
+
+    Ok(Yaixm {
+        airspace,
+        rat: vec![],
+        loa: vec![],
+        obstacle: vec![],
+        service: vec![],
+        release: Release {
+            airac_date,
+            timestamp: now,
+            schema_version: SCHEMA_VERSION,
+            note: "Imported from AIXM 5.1 data".to_string(),
+            commit: "aixm".to_string(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Single volume airspace with segments
+    fn airspace(srs: &str, segments: &str) -> String {
+        format!(
+            r#"<message:AIXMBasicMessage xmlns:message="http://www.aixm.aero/schema/5.1/message"
+  xmlns:aixm="http://www.aixm.aero/schema/5.1" xmlns:gml="http://www.opengis.net/gml/3.2">
+ <message:hasMember><aixm:Airspace gml:id="a1"><aixm:timeSlice>
+  <aixm:AirspaceTimeSlice gml:id="ts1">
+   <aixm:type>D</aixm:type><aixm:designator>TEST</aixm:designator>
+   <aixm:geometryComponent><aixm:AirspaceGeometryComponent gml:id="g1"><aixm:theAirspaceVolume>
+    <aixm:AirspaceVolume gml:id="v1">
+     <aixm:upperLimit uom="FL">95</aixm:upperLimit><aixm:upperLimitReference>STD</aixm:upperLimitReference>
+     <aixm:lowerLimit uom="FT">1500</aixm:lowerLimit><aixm:lowerLimitReference>SFC</aixm:lowerLimitReference>
+     <aixm:horizontalProjection><aixm:Surface gml:id="s1" srsName="{}"><gml:patches>
+      <gml:PolygonPatch><gml:exterior><gml:Ring><gml:curveMember><gml:Curve gml:id="c1">
+       <gml:segments>{}</gml:segments>
+      </gml:Curve></gml:curveMember></gml:Ring></gml:exterior></gml:PolygonPatch>
+     </gml:patches></aixm:Surface></aixm:horizontalProjection>
+    </aixm:AirspaceVolume>
+   </aixm:theAirspaceVolume></aixm:AirspaceGeometryComponent></aixm:geometryComponent>
+  </aixm:AirspaceTimeSlice>
+ </aixm:timeSlice></aixm:Airspace></message:hasMember>
+</message:AIXMBasicMessage>"#,
+            srs, segments
+        )
+    }
+
+    fn arc(start: f64, end: f64) -> String {
+        format!(
+            "<gml:ArcByCenterPoint><gml:pos>52.0 -1.0</gml:pos>\
+             <gml:radius uom=\"[nmi_i]\">5</gml:radius>\
+             <gml:startAngle uom=\"deg\">{}</gml:startAngle>\
+             <gml:endAngle uom=\"deg\">{}</gml:endAngle></gml:ArcByCenterPoint>",
+            start, end
+        )
+    }
+
+    fn boundary(srs: &str, segments: &str) -> Vec<Boundary> {
+        let yaixm = from_aixm(&airspace(srs, segments)).unwrap();
+        yaixm.airspace[0].geometry[0].boundary.clone()
+    }
+
+    #[test]
+    fn volume() {
+        let yaixm = from_aixm(&airspace(
+            "urn:ogc:def:crs:EPSG::4326",
+            "<gml:GeodesicString><gml:posList>52 -1 52 0 51 0 52 -1</gml:posList>\
+             </gml:GeodesicString>",
+        ))
+        .unwrap();
+
+        let feature = &yaixm.airspace[0];
+        assert_eq!(feature.name, "TEST");
+        assert_eq!(feature.icao_type, IcaoType::D);
+        let volume = &feature.geometry[0];
+        assert_eq!(volume.lower, Level::Height(1500));
+        assert_eq!(volume.upper, Level::Fl(95));
+
+        // Closing point is dropped
+        assert_eq!(
+            volume.boundary,
+            [Boundary::Line(vec![
+                LatLon::from_degrees(52.0, -1.0),
+                LatLon::from_degrees(52.0, 0.0),
+                LatLon::from_degrees(51.0, 0.0),
+            ])]
+        );
+    }
+
+    #[test]
+    fn lon_first() {
+        let crs84 = boundary(
+            "urn:ogc:def:crs:OGC:1.3:CRS84",
+            "<gml:GeodesicString><gml:posList>-1 52 0 52 0 51</gml:posList>\
+             </gml:GeodesicString>",
+        );
+        let epsg = boundary(
+            "urn:ogc:def:crs:EPSG::4326",
+            "<gml:GeodesicString><gml:posList>52 -1 52 0 51 0</gml:posList>\
+             </gml:GeodesicString>",
+        );
+        assert_eq!(crs84, epsg);
+    }
+
+    #[test]
+    fn arc_through_north() {
+        let centre = LatLon::from_degrees(52.0, -1.0);
+        let radius = Distance {
+            value: 5.0,
+            unit: DistanceUnit::Nm,
+        };
+
+        for (start, end, from, to, dir) in [
+            (350.0, 370.0, 350.0, 10.0, "cw"),
+            (-10.0, 10.0, 350.0, 10.0, "cw"),
+            (10.0, -10.0, 10.0, 350.0, "ccw"),
+            (370.0, 350.0, 10.0, 350.0, "ccw"),
+        ] {
+            let boundary = boundary("urn:ogc:def:crs:EPSG::4326", &arc(start, end));
+            assert_eq!(
+                boundary,
+                [
+                    Boundary::Line(vec![destination(&centre, from, &radius)]),
+                    Boundary::Arc(Arc {
+                        centre,
+                        dir: dir.to_string(),
+                        radius,
+                        to: destination(&centre, to, &radius),
+                    })
+                ],
+                "{} to {}",
+                start,
+                end
+            );
+        }
+    }
+
+    #[test]
+    fn arc_anticlockwise() {
+        let centre = LatLon::from_degrees(52.0, -1.0);
+        let radius = Distance {
+            value: 5.0,
+            unit: DistanceUnit::Nm,
+        };
+
+        let boundary = boundary("urn:ogc:def:crs:EPSG::4326", &arc(90.0, 0.0));
+        assert_eq!(
+            boundary,
+            [
+                Boundary::Line(vec![destination(&centre, 90.0, &radius)]),
+                Boundary::Arc(Arc {
+                    centre,
+                    dir: "ccw".to_string(),
+                    radius,
+                    to: destination(&centre, 0.0, &radius),
+                })
+            ]
+        );
+    }
+
+    #[test]
+    fn arc_full_circle() {
+        let circle = boundary("urn:ogc:def:crs:EPSG::4326", &arc(0.0, 0.0));
+        assert!(matches!(circle[..], [Boundary::Circle(_)]));
+
+        // Zero length arc in a ring is just a point
+        let ring = boundary(
+            "urn:ogc:def:crs:EPSG::4326",
+            &format!(
+                "<gml:GeodesicString><gml:posList>51 -1 51 0</gml:posList>\
+                 </gml:GeodesicString>{}",
+                arc(90.0, 90.0)
+            ),
+        );
+        assert_eq!(ring.len(), 1);
+        assert!(matches!(&ring[0], Boundary::Line(line) if line.len() == 3));
+    }
+
+    #[test]
+    fn errors() {
+        let bad_pos = airspace(
+            "urn:ogc:def:crs:EPSG::4326",
+            "<gml:GeodesicString><gml:posList>52 -1 x</gml:posList></gml:GeodesicString>",
+        );
+        assert_eq!(
+            from_aixm(&bad_pos).err().unwrap(),
+            "TEST: invalid position \"x\""
+        );
+
+        let bad_segment = airspace("urn:ogc:def:crs:EPSG::4326", "<gml:Clothoid/>");
+        assert_eq!(
+            from_aixm(&bad_segment).err().unwrap(),
+            "TEST: unsupported segment \"Clothoid\""
+        );
+
+        assert!(from_aixm("<message:AIXMBasicMessage/>").is_err());
+    }
+}
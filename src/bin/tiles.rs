@@ -0,0 +1,97 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+use asselect::mvt::{tiles, MAX_ZOOM};
+use asselect::settings::Settings;
+use asselect::yaixm::{parse, DataFormat};
+
+const USAGE: &str =
+    "Usage: tiles <yaixm.json|yaixm.yaml> <output dir> [min zoom] [max zoom] [settings.json]";
+
+// Write vector tiles as <output dir>/z/x/y.pbf
+fn main() -> ExitCode {
+    let args = env::args().collect::<Vec<String>>();
+    if args.len() < 3 {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    }
+    let (path, out_dir) = (&args[1], Path::new(&args[2]));
+
+    let zoom = |n: usize, default: u8| args.get(n).map_or(Ok(default), |z| z.parse::<u8>());
+    let (min_zoom, max_zoom) = match (zoom(3, 5), zoom(4, 10)) {
+        (Ok(min), Ok(max)) if min <= max && max <= MAX_ZOOM => (min, max),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Settings as saved by the web app, default if not given
+    let settings = match args.get(5) {
+        Some(settings_path) => {
+            match fs::read_to_string(settings_path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| serde_json::from_str::<Settings>(&text).map_err(|e| e.to_string()))
+            {
+                Ok(settings) => settings,
+                Err(err) => {
+                    eprintln!("{}: {}", settings_path, err);
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        None => Settings::default(),
+    };
+
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let yaixm = match parse(&text, DataFormat::detect(None, path)) {
+        Ok(yaixm) => yaixm,
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let tiles = match tiles(&yaixm, &settings, min_zoom, max_zoom) {
+        Ok(tiles) => tiles,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    for tile in &tiles {
+        let dir = out_dir.join(tile.z.to_string()).join(tile.x.to_string());
+        let file = dir.join(format!("{}.pbf", tile.y));
+        if let Err(err) = fs::create_dir_all(&dir).and_then(|_| fs::write(&file, &tile.data)) {
+            eprintln!("{}: {}", file.display(), err);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    println!("{} tile(s) written", tiles.len());
+    ExitCode::SUCCESS
+}
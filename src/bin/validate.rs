@@ -0,0 +1,79 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use std::env;
+use std::fs;
+use std::process::ExitCode;
+
+use asselect::lint::lint_openair;
+use asselect::yaixm::{from_yaml_files, parse, validate, DataFormat};
+
+// Check YAIXM data file before deployment, or lint an OpenAir file.
+// Unreleased YAML data can be given as several files
+fn main() -> ExitCode {
+    let paths = env::args().skip(1).collect::<Vec<String>>();
+    let Some(path) = paths.first() else {
+        eprintln!("Usage: validate <yaixm.json|aixm.xml|openair.txt|yaixm.yaml...>");
+        return ExitCode::FAILURE;
+    };
+
+    let mut texts = vec![];
+    for path in &paths {
+        match fs::read_to_string(path) {
+            Ok(text) => texts.push(text),
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let text = &texts[0];
+
+    let lower = path.to_lowercase();
+    let issues = if lower.ends_with(".txt") || lower.ends_with(".air") {
+        lint_openair(text)
+    } else {
+        let yaixm = if paths.len() > 1 {
+            if paths
+                .iter()
+                .any(|p| DataFormat::detect(None, p) != DataFormat::Yaml)
+            {
+                eprintln!("Only YAML data can be split across files");
+                return ExitCode::FAILURE;
+            }
+            from_yaml_files(&texts.iter().map(String::as_str).collect::<Vec<&str>>())
+        } else {
+            parse(text, DataFormat::detect(None, path))
+        };
+
+        match yaixm {
+            Ok(yaixm) => validate(&yaixm),
+            Err(err) => {
+                eprintln!("{}: {}", paths.join(", "), err);
+                return ExitCode::FAILURE;
+            }
+        }
+    };
+    for issue in &issues {
+        println!("{}", issue);
+    }
+
+    if issues.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        eprintln!("{} problem(s) found", issues.len());
+        ExitCode::FAILURE
+    }
+}
@@ -15,9 +15,14 @@
 //
 pub mod about_tab;
 pub mod airspace_tab;
+pub mod data_file;
 pub mod extra_panel;
 pub mod extra_tab;
+pub mod flight_tab;
+pub mod issue_report;
 pub mod notam_tab;
 pub mod option_tab;
+pub mod query_tab;
+pub mod route_tab;
 pub mod select_field;
 pub mod tabs;
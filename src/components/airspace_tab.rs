@@ -17,7 +17,7 @@ use leptos::html::div;
 use leptos::prelude::*;
 
 use crate::components::select_field::select_field;
-use crate::settings::{AirType, Settings};
+use asselect::settings::{AirType, Settings};
 
 pub fn airspace_tab(gliding_sites: Vec<String>) -> impl IntoView {
     let setter = use_context::<WriteSignal<Settings>>().expect("to find setter");
@@ -0,0 +1,91 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use gloo::file::{futures::read_as_text, File};
+use leptos::ev;
+use leptos::html::{div, input, label, p, span};
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use web_sys::HtmlInputElement;
+
+use asselect::yaixm::{self, DataFormat, Yaixm};
+
+pub fn data_file() -> impl IntoView {
+    let setter = use_context::<WriteSignal<Option<Result<Yaixm, String>>>>()
+        .expect("to find local data setter");
+
+    let (dragging, set_dragging) = signal(false);
+
+    // Read and parse local file
+    let load = move |file: web_sys::File| {
+        let file = File::from(file);
+        spawn_local(async move {
+            let format = DataFormat::detect(Some(&file.raw_mime_type()), &file.name());
+            let result = match read_as_text(&file).await {
+                Ok(text) => yaixm::parse(&text, format),
+                Err(err) => Err(err.to_string()),
+            };
+            setter.set(Some(result));
+        });
+    };
+
+    div()
+        .class(move || {
+            if dragging.get() {
+                "box has-background-primary-soft"
+            } else {
+                "box"
+            }
+        })
+        .on(ev::dragover, move |ev| {
+            ev.prevent_default();
+            set_dragging.set(true);
+        })
+        .on(ev::dragleave, move |_| set_dragging.set(false))
+        .on(ev::drop, move |ev| {
+            ev.prevent_default();
+            set_dragging.set(false);
+            if let Some(file) = ev
+                .data_transfer()
+                .and_then(|dt| dt.files())
+                .and_then(|files| files.get(0))
+            {
+                load(file);
+            }
+        })
+        .child((
+            div().class("file").child(
+                label().class("file-label").child((
+                    input()
+                        .r#type("file")
+                        .class("file-input")
+                        .attr("accept", ".json,.yaml,.yml,.xml")
+                        .on(ev::change, move |ev| {
+                            let input = event_target::<HtmlInputElement>(&ev);
+                            if let Some(file) = input.files().and_then(|files| files.get(0)) {
+                                load(file);
+                            }
+                        }),
+                    span().class("file-cta").child(
+                        span()
+                            .class("file-label")
+                            .child("Load local YAIXM or AIXM file..."),
+                    ),
+                )),
+            ),
+            p().class("mt-2")
+                .child("or drop a JSON/YAML/AIXM file here"),
+        ))
+}
@@ -17,13 +17,13 @@ use leptos::ev;
 use leptos::html::{div, input, label, p};
 use leptos::prelude::*;
 
-use crate::settings::{ExtraType, Settings};
+use asselect::settings::{ExtraType, Settings};
 
 pub fn extra_panel(names: Vec<String>, id: ExtraType) -> impl IntoView {
     let setter = use_context::<WriteSignal<Settings>>().expect("to find setter");
     let getter = use_context::<ReadSignal<Settings>>().expect("to find getter");
 
-    if names.len() > 0 {
+    if !names.is_empty() {
         div()
             .class("columns is-multiline")
             .child(
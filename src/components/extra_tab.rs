@@ -17,7 +17,7 @@ use leptos::ev;
 use leptos::html::{div, header, input, p};
 use leptos::prelude::*;
 
-use crate::settings::{ExtraType, Settings};
+use asselect::settings::{ExtraType, Settings};
 
 pub fn extra_tab(children: Vec<AnyView>, names: Vec<&str>, ids: Vec<ExtraType>) -> impl IntoView {
     let setter = use_context::<WriteSignal<Settings>>().expect("to find setter");
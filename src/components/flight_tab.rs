@@ -0,0 +1,174 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use gloo::file::{futures::read_as_text, File};
+use leptos::ev;
+use leptos::html::{div, input, label, option, p, select, span, table, tbody, td, th, thead, tr};
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use web_sys::HtmlInputElement;
+
+use asselect::igc::{check_flight, format_time, parse_igc, AltitudeSource, Fix, Infringement};
+use asselect::settings::Settings;
+use asselect::yaixm::Yaixm;
+
+pub fn flight_tab(yaixm: Signal<Yaixm>) -> impl IntoView {
+    let getter = use_context::<ReadSignal<Settings>>().expect("to find getter");
+
+    let (fixes, set_fixes) = signal(None::<Result<Vec<Fix>, String>>);
+    // Altitude for checking altitude limits, flight levels always use
+    // pressure altitude
+    let (use_qnh, set_use_qnh) = signal(false);
+    let (qnh, set_qnh) = signal(1013.0);
+    let source = move || {
+        if use_qnh.get() {
+            AltitudeSource::Qnh(qnh.get())
+        } else {
+            AltitudeSource::Gnss
+        }
+    };
+
+    // Load flight log
+    let load_igc = move |ev| {
+        let input = event_target::<HtmlInputElement>(&ev);
+        if let Some(file) = input.files().and_then(|files| files.get(0)) {
+            let file = File::from(file);
+            spawn_local(async move {
+                let res = match read_as_text(&file).await {
+                    Ok(text) => parse_igc(&text),
+                    Err(err) => Err(err.to_string()),
+                };
+                set_fixes.set(Some(res));
+            });
+        }
+    };
+
+    // Re-check when flight, altitude source, settings or airspace change
+    let result = Memo::new(move |_| {
+        fixes
+            .get()
+            .map(|res| res.map(|fixes| check_flight(&yaixm.get(), &getter.get(), &fixes, source())))
+    });
+
+    div().child((
+        div().class("box").child(
+            div().class("columns").child((
+                div().class("column is-one-third").child(
+                    div().class("field").child(
+                        label().class("label").child((
+                            "Flight Log",
+                            div().class("control").child(
+                                div().class("file").child(
+                                    label().class("file-label").child((
+                                        input()
+                                            .r#type("file")
+                                            .class("file-input")
+                                            .attr("accept", ".igc")
+                                            .on(ev::change, load_igc),
+                                        span().class("file-cta").child(
+                                            span().class("file-label").child("Load IGC file..."),
+                                        ),
+                                    )),
+                                ),
+                            ),
+                        )),
+                    ),
+                ),
+                div().class("column is-one-third").child(
+                    div().class("field").child(
+                        label().class("label").child((
+                            "Altitude",
+                            div().class("control").child(
+                                div().class("select is-fullwidth").child(
+                                    select()
+                                        .on(ev::change, move |ev| {
+                                            set_use_qnh.set(event_target_value(&ev) == "qnh")
+                                        })
+                                        .child((
+                                            option().value("gnss").child("GNSS"),
+                                            option().value("qnh").child("Pressure with QNH"),
+                                        )),
+                                ),
+                            ),
+                        )),
+                    ),
+                ),
+                div().class("column is-one-third").child(
+                    div().class("field").child(
+                        label().class("label").child((
+                            "QNH (hPa)",
+                            div().class("control").child(
+                                input()
+                                    .r#type("number")
+                                    .class("input")
+                                    .attr("min", "900")
+                                    .attr("max", "1100")
+                                    .prop("value", move || qnh.get().to_string())
+                                    .prop("disabled", move || !use_qnh.get())
+                                    .on(ev::change, move |ev| {
+                                        if let Ok(value) = event_target_value(&ev).parse() {
+                                            set_qnh.set(value);
+                                        }
+                                    }),
+                            ),
+                        )),
+                    ),
+                ),
+            )),
+        ),
+        move || match result.get() {
+            Some(Ok(infringements)) if infringements.is_empty() => {
+                p().child("No airspace infringements").into_any()
+            }
+            Some(Ok(infringements)) => infringement_table(infringements).into_any(),
+            Some(Err(err)) => p().class("has-text-danger").child(err).into_any(),
+            None => ().into_any(),
+        },
+    ))
+}
+
+fn infringement_table(infringements: Vec<Infringement>) -> impl IntoView {
+    table().class("table is-fullwidth is-striped").child((
+        thead().child(tr().child((
+            th().child("Name"),
+            th().child("Type"),
+            th().child("Start (UTC)"),
+            th().child("End (UTC)"),
+            th().child("Duration"),
+            th().child("Vertical (ft)"),
+            th().child("Horizontal (m)"),
+            th().child("Lower"),
+            th().child("Upper"),
+        ))),
+        tbody().child(
+            infringements
+                .into_iter()
+                .map(|inf| {
+                    tr().child((
+                        td().child(inf.name.clone()),
+                        td().child(inf.airtype.to_string()),
+                        td().child(format_time(inf.start)),
+                        td().child(format_time(inf.end)),
+                        td().child(format_time(inf.duration())),
+                        td().child(format!("{:.0}", inf.vertical)),
+                        td().child(format!("{:.0}", inf.horizontal)),
+                        td().child(inf.lower.to_string()),
+                        td().child(inf.upper.to_string()),
+                    ))
+                })
+                .collect_view(),
+        ),
+    ))
+}
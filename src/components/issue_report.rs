@@ -0,0 +1,39 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use leptos::html::{div, li, p, ul};
+use leptos::prelude::*;
+
+use asselect::yaixm::Issue;
+
+// Warning listing issues, heading shown before the list
+pub fn issue_report(heading: String, issues: Vec<Issue>) -> impl IntoView {
+    if issues.is_empty() {
+        ().into_any()
+    } else {
+        div()
+            .class("notification is-warning is-light")
+            .child((
+                p().child(heading),
+                ul().class("ml-4").child(
+                    issues
+                        .into_iter()
+                        .map(|issue| li().child(issue.to_string()))
+                        .collect_view(),
+                ),
+            ))
+            .into_any()
+    }
+}
@@ -13,112 +13,303 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 //
-use leptos::html::div;
+use gloo::file::{futures::read_as_text, File};
+use leptos::ev;
+use leptos::html::{button, div, h2, input, label, p, span};
 use leptos::prelude::*;
+use leptos::task::spawn_local;
+use strum::IntoEnumIterator;
+use web_sys::HtmlInputElement;
 
+use crate::components::issue_report::issue_report;
 use crate::components::select_field::select_field;
-use crate::settings::{AirType, Format, Overlay, Settings};
+use asselect::convert::parse_openair;
+use asselect::lint::lint_openair;
+use asselect::settings::{AirType, Format, Mode, Overlay, Settings};
+use asselect::yaixm::{Feature, Issue};
 
 pub fn option_tab() -> impl IntoView {
     let setter = use_context::<WriteSignal<Settings>>().expect("to find setter");
     let getter = use_context::<ReadSignal<Settings>>().expect("to find getter");
 
-    div().child(div().class("box").child((
-        div().class("columns").child((
-            div().class("column is-one-third").child(select_field(
-                setter,
-                Signal::derive(move || getter.get().format.to_string()),
-                "Format",
-                "format",
-                &vec!["OpenAir", "RA(T) Only", "Competition"],
-                &vec![
-                    Format::OpenAir.as_ref(),
-                    Format::RatOnly.as_ref(),
-                    Format::Competition.as_ref(),
-                ],
+    div().child((
+        div().class("box").child((
+            div().class("columns").child((
+                div().class("column is-one-third").child(select_field(
+                    setter,
+                    Signal::derive(move || getter.get().format.to_string()),
+                    "Format",
+                    "format",
+                    &vec![
+                        "OpenAir",
+                        "OpenAir Extended",
+                        "GeoJSON",
+                        "KML (3D)",
+                        "TNP",
+                        "SeeYou (CUB)",
+                        "Shapefile (zip)",
+                    ],
+                    &vec![
+                        Format::OpenAir.as_ref(),
+                        Format::OpenAirExtended.as_ref(),
+                        Format::GeoJson.as_ref(),
+                        Format::Kml.as_ref(),
+                        Format::Tnp.as_ref(),
+                        Format::Cub.as_ref(),
+                        Format::Shapefile.as_ref(),
+                    ],
+                )),
+                div().class("column is-one-third").child(select_field(
+                    setter,
+                    Signal::derive(move || getter.get().mode.to_string()),
+                    "Mode",
+                    "mode",
+                    &vec!["Normal", "RA(T) Only", "Competition"],
+                    &vec![
+                        Mode::Normal.as_ref(),
+                        Mode::RatOnly.as_ref(),
+                        Mode::Competition.as_ref(),
+                    ],
+                )),
+                div().class("column is-one-third").child(select_field(
+                    setter,
+                    Signal::derive(move || getter.get().max_level.to_string()),
+                    "Maximum Level",
+                    "max_level",
+                    &vec!["Unlimited", "FL195", "FL125", "FL105", "FL65"],
+                    &vec!["660", "195", "125", "105", "65"],
+                )),
             )),
-            div().class("column is-one-third").child(select_field(
-                setter,
-                Signal::derive(move || getter.get().max_level.to_string()),
-                "Maximum Level",
-                "max_level",
-                &vec!["Unlimited", "FL195", "FL125", "FL105", "FL65"],
-                &vec!["660", "195", "125", "105", "65"],
+            div().class("columns").child((
+                div().class("column is-one-third").child(select_field(
+                    setter,
+                    Signal::derive(move || {
+                        getter
+                            .get()
+                            .hirta_gvs
+                            .map_or("no".to_string(), |v| v.to_string())
+                    }),
+                    "HIRTA/GVS",
+                    "hirta_gvs",
+                    &vec!["No", "Danger", "Restricted"],
+                    &vec!["no", AirType::Danger.as_ref(), AirType::Restricted.as_ref()],
+                )),
+                div().class("column is-one-third").child(select_field(
+                    setter,
+                    Signal::derive(move || {
+                        getter
+                            .get()
+                            .obstacle
+                            .map_or("no".to_string(), |v| v.to_string())
+                    }),
+                    "Obstacle",
+                    "obstacle",
+                    &vec!["No", "Danger", "Class F", "Class G"],
+                    &vec![
+                        "no",
+                        AirType::Danger.as_ref(),
+                        AirType::ClassF.as_ref(),
+                        AirType::ClassG.as_ref(),
+                    ],
+                )),
             )),
-        )),
-        div().class("columns").child((
-            div().class("column is-one-third").child(select_field(
-                setter,
-                Signal::derive(move || {
-                    getter
-                        .get()
-                        .hirta_gvs
-                        .map_or("no".to_string(), |v| v.to_string())
-                }),
-                "HIRTA/GVS",
-                "hirta_gvs",
-                &vec!["No", "Danger", "Restricted"],
-                &vec!["no", AirType::Danger.as_ref(), AirType::Restricted.as_ref()],
-            )),
-            div().class("column is-one-third").child(select_field(
-                setter,
-                Signal::derive(move || {
-                    getter
-                        .get()
-                        .obstacle
-                        .map_or("no".to_string(), |v| v.to_string())
-                }),
-                "Obstacle",
-                "obstacle",
-                &vec!["No", "Danger", "Class F", "Class G"],
-                &vec![
-                    "no",
-                    AirType::Danger.as_ref(),
-                    AirType::ClassF.as_ref(),
-                    AirType::ClassG.as_ref(),
-                ],
+            div().class("columns").child((
+                div().class("column is-one-third").child(select_field(
+                    setter,
+                    Signal::derive(move || {
+                        if getter.get().radio {
+                            "yes".to_string()
+                        } else {
+                            "no".to_string()
+                        }
+                    }),
+                    "Radio Frequency",
+                    "radio",
+                    &vec!["No", "Add to name"],
+                    &vec!["no", "yes"],
+                )),
+                div().class("column is-one-third").child(select_field(
+                    setter,
+                    Signal::derive(move || {
+                        if getter.get().styles {
+                            "yes".to_string()
+                        } else {
+                            "no".to_string()
+                        }
+                    }),
+                    "OpenAir Colours",
+                    "styles",
+                    &vec!["No", "Add SP/SB records"],
+                    &vec!["no", "yes"],
+                )),
+                div().class("column is-one-third").child(select_field(
+                    setter,
+                    Signal::derive(move || {
+                        getter
+                            .get()
+                            .overlay
+                            .map_or("no".to_string(), |v| v.to_string())
+                    }),
+                    "Altitude Overlay",
+                    "overlay",
+                    &vec![
+                        "No",
+                        "Bases to FL195",
+                        "Bases to FL105",
+                        "Bases to FL105 and ATZ/DZ",
+                        "Bases to FL105 and ATZ/DZ (Overlay only)",
+                    ],
+                    &vec![
+                        "no",
+                        Overlay::FL195.as_ref(),
+                        Overlay::FL105.as_ref(),
+                        Overlay::AtzDz.as_ref(),
+                        Overlay::AtzDzOnly.as_ref(),
+                    ],
+                )),
             )),
         )),
-        div().class("columns").child((
-            div().class("column is-one-third").child(select_field(
-                setter,
-                Signal::derive(move || {
-                    if getter.get().radio {
-                        "yes".to_string()
-                    } else {
-                        "no".to_string()
-                    }
-                }),
-                "Radio Frequency",
-                "radio",
-                &vec!["No", "Add to name"],
-                &vec!["no", "yes"],
+        colour_box(),
+        user_box(),
+    ))
+}
+
+// Additional airspace from user OpenAir file, checked for problems
+fn user_box() -> impl IntoView {
+    let getter = use_context::<ReadSignal<Vec<Feature>>>().expect("to find user airspace");
+    let setter = use_context::<WriteSignal<Vec<Feature>>>().expect("to find user airspace");
+
+    let (error, set_error) = signal(None::<String>);
+    let (issues, set_issues) = signal(Vec::<Issue>::new());
+
+    let load = move |file: web_sys::File| {
+        let file = File::from(file);
+        spawn_local(async move {
+            let result = match read_as_text(&file).await {
+                Ok(text) => {
+                    set_issues.set(lint_openair(&text));
+                    parse_openair(&text)
+                }
+                Err(err) => Err(err.to_string()),
+            };
+            match result {
+                Ok(features) => {
+                    setter.set(features);
+                    set_error.set(None);
+                }
+                Err(err) => {
+                    setter.set(vec![]);
+                    set_error.set(Some(err));
+                }
+            }
+        });
+    };
+
+    div().class("box").child((
+        h2().class("subtitle")
+            .child("Additional Airspace (OpenAir)"),
+        div().class("file").child(
+            label().class("file-label").child((
+                input()
+                    .r#type("file")
+                    .class("file-input")
+                    .attr("accept", ".txt,.air,.openair")
+                    .on(ev::change, move |ev| {
+                        let input = event_target::<HtmlInputElement>(&ev);
+                        if let Some(file) = input.files().and_then(|files| files.get(0)) {
+                            load(file);
+                        }
+                        input.set_value("");
+                    }),
+                span()
+                    .class("file-cta")
+                    .child(span().class("file-label").child("Load OpenAir file...")),
             )),
-            div().class("column is-one-third").child(select_field(
-                setter,
-                Signal::derive(move || {
-                    getter
-                        .get()
-                        .overlay
-                        .map_or("no".to_string(), |v| v.to_string())
-                }),
-                "Altitude Overlay",
-                "overlay",
-                &vec![
-                    "No",
-                    "Bases to FL195",
-                    "Bases to FL105",
-                    "Bases to FL105 and ATZ/DZ",
-                    "Bases to FL105 and ATZ/DZ (Overlay only)",
-                ],
-                &vec![
-                    "no",
-                    Overlay::FL195.as_ref(),
-                    Overlay::FL105.as_ref(),
-                    Overlay::AtzDz.as_ref(),
-                    Overlay::AtzDzOnly.as_ref(),
-                ],
+        ),
+        p().class("mt-2").child(move || match error.get() {
+            Some(err) => format!("Error: {}", err),
+            None => match getter.get().len() {
+                0 => "No additional airspace".to_string(),
+                n => format!("{} airspace(s) added to download", n),
+            },
+        }),
+        div().class("mt-2").child(move || {
+            let issues = issues.get();
+            issue_report(
+                format!(
+                    "File has {} problem(s), some devices may reject it:",
+                    issues.len()
+                ),
+                issues,
+            )
+        }),
+        button()
+            .class("button is-small mt-2")
+            .on(ev::click, move |_| {
+                setter.set(vec![]);
+                set_error.set(None);
+                set_issues.set(vec![]);
+            })
+            .child("Clear"),
+    ))
+}
+
+// OpenAir colour for each airspace type
+fn colour_box() -> impl IntoView {
+    let setter = use_context::<WriteSignal<Settings>>().expect("to find setter");
+
+    div().class("box").child((
+        h2().class("subtitle").child("Colours"),
+        div()
+            .class("columns is-multiline")
+            .child(AirType::iter().map(colour_field).collect_view()),
+        button()
+            .class("button is-small")
+            .on(ev::click, move |_| setter.update(|s| s.pens.clear()))
+            .child("Reset Colours"),
+    ))
+}
+
+fn colour_field(airtype: AirType) -> impl IntoView {
+    let setter = use_context::<WriteSignal<Settings>>().expect("to find setter");
+    let getter = use_context::<ReadSignal<Settings>>().expect("to find getter");
+
+    div().class("column is-one-quarter").child(
+        div().class("field").child(
+            label().class("label").child((
+                airtype_name(airtype),
+                div().class("control").child(
+                    input()
+                        .r#type("color")
+                        .class("input")
+                        .prop("value", move || getter.get().pen(airtype).colour)
+                        .on(ev::input, move |ev| {
+                            setter.update(|s| s.set_colour(airtype, &event_target_value(&ev)))
+                        }),
+                ),
             )),
-        )),
-    )))
+        ),
+    )
+}
+
+fn airtype_name(airtype: AirType) -> &'static str {
+    match airtype {
+        AirType::ClassA => "Class A",
+        AirType::ClassB => "Class B",
+        AirType::ClassC => "Class C",
+        AirType::ClassD => "Class D",
+        AirType::ClassE => "Class E",
+        AirType::ClassF => "Class F",
+        AirType::ClassG => "Class G",
+        AirType::Danger => "Danger",
+        AirType::Cta => "CTA",
+        AirType::Ctr => "CTR",
+        AirType::Gliding => "Gliding Sector",
+        AirType::Matz => "MATZ",
+        AirType::Other => "Other",
+        AirType::Prohibited => "Prohibited",
+        AirType::Restricted => "Restricted",
+        AirType::Rmz => "RMZ",
+        AirType::Tmz => "TMZ",
+    }
 }
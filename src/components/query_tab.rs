@@ -0,0 +1,122 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use leptos::ev;
+use leptos::html::{button, div, input, label, option, p, select, table, tbody, td, th, thead, tr};
+use leptos::prelude::*;
+
+use asselect::query::{airspace_at, parse_position, site_position, QueryResult};
+use asselect::settings::Settings;
+use asselect::yaixm::Yaixm;
+
+pub fn query_tab(yaixm: Signal<Yaixm>, gliding_sites: Vec<String>) -> impl IntoView {
+    let getter = use_context::<ReadSignal<Settings>>().expect("to find getter");
+
+    let (position, set_position) = signal(String::new());
+    let (result, set_result) = signal(None::<Result<Vec<QueryResult>, String>>);
+
+    // Fill in position from gliding site
+    let select_site = move |ev| {
+        if let Some(latlon) = site_position(&yaixm.get_untracked(), &event_target_value(&ev)) {
+            set_position.set(latlon.to_string());
+        }
+    };
+
+    let search = move |_| {
+        let res = parse_position(&position.get_untracked())
+            .map(|latlon| airspace_at(&yaixm.get_untracked(), &getter.get_untracked(), &latlon));
+        set_result.set(Some(res));
+    };
+
+    div().child((
+        div().class("box").child((
+            div().class("columns").child((
+                div().class("column is-one-third").child(
+                    div().class("field").child(
+                        label().class("label").child((
+                            "Gliding Site",
+                            div().class("control").child(
+                                div().class("select is-fullwidth").child(
+                                    select().on(ev::change, select_site).child(
+                                        std::iter::once("".to_string())
+                                            .chain(gliding_sites)
+                                            .map(|site| option().value(site.clone()).child(site))
+                                            .collect_view(),
+                                    ),
+                                ),
+                            ),
+                        )),
+                    ),
+                ),
+                div().class("column is-one-third").child(
+                    div().class("field").child(
+                        label().class("label").child((
+                            "Position",
+                            div().class("control").child(
+                                input()
+                                    .class("input")
+                                    .r#type("text")
+                                    .placeholder("512345N 0012345W or 51.4, -1.2")
+                                    .prop("value", move || position.get())
+                                    .on(ev::input, move |ev| {
+                                        set_position.set(event_target_value(&ev))
+                                    }),
+                            ),
+                        )),
+                    ),
+                ),
+            )),
+            button()
+                .class("button is-info")
+                .on(ev::click, search)
+                .child("Find Airspace"),
+        )),
+        move || match result.get() {
+            Some(Ok(volumes)) if volumes.is_empty() => {
+                p().child("No airspace above this position").into_any()
+            }
+            Some(Ok(volumes)) => table()
+                .class("table is-fullwidth is-striped")
+                .child((
+                    thead().child(tr().child((
+                        th().child("Name"),
+                        th().child("Type"),
+                        th().child("Lower"),
+                        th().child("Upper"),
+                        th().child("Frequency"),
+                    ))),
+                    tbody().child(
+                        volumes
+                            .into_iter()
+                            .map(|v| {
+                                tr().child((
+                                    td().child(v.name),
+                                    td().child(v.airtype.to_string()),
+                                    td().child(v.lower.to_string()),
+                                    td().child(v.upper.to_string()),
+                                    td().child(
+                                        v.frequency.map_or("".to_string(), |f| format!("{:.3}", f)),
+                                    ),
+                                ))
+                            })
+                            .collect_view(),
+                    ),
+                ))
+                .into_any(),
+            Some(Err(err)) => p().class("has-text-danger").child(err).into_any(),
+            None => ().into_any(),
+        },
+    ))
+}
@@ -0,0 +1,193 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use gloo::file::{futures::read_as_text, Blob, File, ObjectUrl};
+use leptos::ev;
+use leptos::html::{
+    a, button, div, input, label, p, span, table, tbody, td, textarea, th, thead, tr, A,
+};
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use web_sys::HtmlInputElement;
+
+use asselect::profile::profile_svg;
+use asselect::route::{format_route, parse_route, parse_task_file, route_crossings, Crossing};
+use asselect::settings::Settings;
+use asselect::yaixm::Yaixm;
+
+#[derive(Clone)]
+struct RouteResult {
+    legs: Vec<String>,
+    crossings: Vec<Crossing>,
+    profile: String,
+}
+
+pub fn route_tab(yaixm: Signal<Yaixm>) -> impl IntoView {
+    let getter = use_context::<ReadSignal<Settings>>().expect("to find getter");
+
+    let (route_text, set_route_text) = signal(String::new());
+    let (result, set_result) = signal(None::<Result<RouteResult, String>>);
+
+    // Load turnpoints from task file
+    let load_task = move |ev| {
+        let input = event_target::<HtmlInputElement>(&ev);
+        if let Some(file) = input.files().and_then(|files| files.get(0)) {
+            let file = File::from(file);
+            spawn_local(async move {
+                match read_as_text(&file).await {
+                    Ok(text) => match parse_task_file(&file.name(), &text) {
+                        Ok(route) => set_route_text.set(format_route(&route)),
+                        Err(err) => set_result.set(Some(Err(err))),
+                    },
+                    Err(err) => set_result.set(Some(Err(err.to_string()))),
+                }
+            });
+        }
+    };
+
+    let check = move |_| {
+        let yaixm = yaixm.get_untracked();
+        let res = parse_route(&yaixm, &route_text.get_untracked()).and_then(|route| {
+            if route.len() < 2 {
+                return Err("route needs at least two turnpoints".to_string());
+            }
+            let settings = getter.get_untracked();
+            let crossings = route_crossings(&yaixm, &settings, &route);
+
+            // Profile up to maximum level, but no higher than FL195
+            let ceiling = u32::from(settings.max_level.min(195)) * 100;
+
+            Ok(RouteResult {
+                legs: route
+                    .windows(2)
+                    .map(|tps| format!("{} - {}", tps[0].name, tps[1].name))
+                    .collect(),
+                profile: profile_svg(&route, &crossings, ceiling),
+                crossings,
+            })
+        });
+        set_result.set(Some(res));
+    };
+
+    div().child((
+        div().class("box").child((
+            div().class("field").child(
+                label().class("label").child((
+                    "Turnpoints",
+                    div().class("control").child(
+                        textarea()
+                            .class("textarea")
+                            .rows(6)
+                            .placeholder("One per line: gliding site, position, or name: position")
+                            .prop("value", move || route_text.get())
+                            .on(ev::input, move |ev| {
+                                set_route_text.set(event_target_value(&ev))
+                            }),
+                    ),
+                )),
+            ),
+            div().class("field is-grouped").child((
+                div().class("control").child(
+                    button()
+                        .class("button is-info")
+                        .on(ev::click, check)
+                        .child("Check Route"),
+                ),
+                div().class("control").child(
+                    div().class("file").child(
+                        label().class("file-label").child((
+                            input()
+                                .r#type("file")
+                                .class("file-input")
+                                .attr("accept", ".cup,.tsk")
+                                .on(ev::change, load_task),
+                            span()
+                                .class("file-cta")
+                                .child(span().class("file-label").child("Import .cup/.tsk...")),
+                        )),
+                    ),
+                ),
+            )),
+        )),
+        move || match result.get() {
+            Some(Ok(res)) if res.crossings.is_empty() => {
+                p().child("Route doesn't cross any airspace").into_any()
+            }
+            Some(Ok(res)) => (
+                crossing_table(res.legs, res.crossings),
+                profile_view(res.profile),
+            )
+                .into_any(),
+            Some(Err(err)) => p().class("has-text-danger").child(err).into_any(),
+            None => ().into_any(),
+        },
+    ))
+}
+
+fn crossing_table(legs: Vec<String>, crossings: Vec<Crossing>) -> impl IntoView {
+    table().class("table is-fullwidth is-striped").child((
+        thead().child(tr().child((
+            th().child("Leg"),
+            th().child("Name"),
+            th().child("Type"),
+            th().child("Entry (km)"),
+            th().child("Exit (km)"),
+            th().child("Lower"),
+            th().child("Upper"),
+            th().child("Frequency"),
+        ))),
+        tbody().child(
+            crossings
+                .into_iter()
+                .map(|c| {
+                    tr().child((
+                        td().child(legs[c.leg].clone()),
+                        td().child(c.name),
+                        td().child(c.airtype.to_string()),
+                        td().child(format!("{:.1}", c.entry / 1000.0)),
+                        td().child(format!("{:.1}", c.exit / 1000.0)),
+                        td().child(c.lower.to_string()),
+                        td().child(c.upper.to_string()),
+                        td().child(c.frequency.map_or("".to_string(), |f| format!("{:.3}", f))),
+                    ))
+                })
+                .collect_view(),
+        ),
+    ))
+}
+
+fn profile_view(svg: String) -> impl IntoView {
+    let download_node_ref = NodeRef::<A>::new();
+
+    let svg_data = svg.clone();
+    let download = move |_| {
+        let blob = Blob::new_with_options(svg_data.as_str(), Some("image/svg+xml"));
+        let object_url = ObjectUrl::from(blob);
+
+        let a = download_node_ref.get().unwrap();
+        a.set_download("profile.svg");
+        a.set_href(&object_url);
+        a.click();
+    };
+
+    div().class("block").child((
+        div().class("box").inner_html(svg),
+        button()
+            .class("button is-info is-soft")
+            .on(ev::click, download)
+            .child("Download Profile"),
+        a().hidden(true).node_ref(download_node_ref),
+    ))
+}
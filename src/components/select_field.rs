@@ -17,7 +17,7 @@ use leptos::ev;
 use leptos::html::{div, label, option, select};
 use leptos::prelude::*;
 
-use crate::Settings;
+use asselect::settings::Settings;
 
 pub fn select_field(
     setter: WriteSignal<Settings>,
@@ -13,13 +13,14 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 //
-use crate::settings::{AirType, Format, Settings};
+use crate::geometry::arc_points;
+use crate::settings::{AirType, Format, Mode, Pen, Settings};
 use crate::yaixm::{
-    latlon_to_degrees, radius_to_metres, Arc, Boundary, Circle, Feature, IcaoClass, IcaoType, Loa,
-    LocalType, Obstacle, Rule, Service, Volume, Yaixm,
+    Arc, Boundary, Circle, Distance, Feature, IcaoClass, IcaoType, LatLon, Level, Loa, LocalType,
+    Obstacle, Rule, Service, Volume, Yaixm,
 };
 use chrono::Utc;
-use geo::{Bearing, Destination, Geodesic, Point};
+use geo::{Destination, Distance as _, Geodesic};
 use std::collections::{HashMap, HashSet};
 
 impl LocalType {
@@ -42,8 +43,22 @@ impl LocalType {
     }
 }
 
+impl IcaoClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IcaoClass::A => "A",
+            IcaoClass::B => "B",
+            IcaoClass::C => "C",
+            IcaoClass::D => "D",
+            IcaoClass::E => "E",
+            IcaoClass::F => "F",
+            IcaoClass::G => "G",
+        }
+    }
+}
+
 impl Rule {
-    fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &'static str {
         match self {
             Rule::Intense => "INTENSE",
             Rule::Loa => "LOA",
@@ -82,25 +97,40 @@ impl AirType {
     }
 }
 
-// Normalise all levels to flight level
-fn norm_level(value: &str) -> u16 {
-    if let Some(fl) = value.strip_prefix("FL") {
-        fl.parse().unwrap()
-    } else if value.ends_with(" ft") {
-        value.split(' ').next().unwrap().parse::<u16>().unwrap() / 100
-    } else {
-        0
-    }
+// Extended OpenAir class
+fn extended_class(sel: &SelectedVolume) -> &'static str {
+    sel.icao_class().map_or("UNCLASSIFIED", |c| c.as_str())
 }
 
-// Openair level format
-fn format_level(level: &str) -> String {
-    if let Some(alt) = level.strip_suffix(" ft") {
-        // Altitude
-        alt.to_string() + " ft"
-    } else {
-        // Flight level or SFC
-        level.to_string()
+// Extended OpenAir type
+fn extended_type(sel: &SelectedVolume) -> &'static str {
+    // Type chosen in settings takes priority
+    match sel.airtype {
+        AirType::Danger => return "DANGER",
+        AirType::Restricted => return "RESTRICTED",
+        AirType::Prohibited => return "PROHIBITED",
+        AirType::Gliding => return "GLIDING_SECTOR",
+        _ => (),
+    }
+
+    match sel.feature.local_type {
+        Some(LocalType::Matz) => "MATZ",
+        Some(LocalType::Rmz) => "RMZ",
+        Some(LocalType::Tmz) => "TMZ",
+        Some(LocalType::Glider) => "GLIDING_SECTOR",
+        Some(LocalType::Dz) => "AERIAL_SPORTING_RECREATIONAL",
+        Some(LocalType::NoAtz) | Some(LocalType::Ul) => "UNKNOWN",
+        _ => match sel.feature.icao_type {
+            IcaoType::Atz => "ATZ",
+            IcaoType::Awy => "AIRWAY",
+            IcaoType::Cta => "CTA",
+            IcaoType::Ctr => "CTR",
+            IcaoType::D | IcaoType::DOther => "DANGER",
+            IcaoType::P => "PROHIBITED",
+            IcaoType::R => "RESTRICTED",
+            IcaoType::Tma => "TMA",
+            IcaoType::Other => "UNKNOWN",
+        },
     }
 }
 
@@ -127,36 +157,34 @@ fn resolution(airtype: AirType) -> u32 {
 }
 
 // Openair lat/lon format
-fn format_latlon(latlon: &str) -> String {
+fn format_latlon(latlon: &LatLon) -> String {
+    let (lat, lon) = (latlon.lat.abs(), latlon.lon.abs());
     format!(
-        "{}:{}:{} {} {}:{}:{} {}",
-        &latlon[..2],
-        &latlon[2..4],
-        &latlon[4..6],
-        &latlon[6..7],
-        &latlon[8..11],
-        &latlon[11..13],
-        &latlon[13..15],
-        &latlon[15..16]
+        "{:02}:{:02}:{:02} {} {:03}:{:02}:{:02} {}",
+        lat / 3600,
+        (lat / 60) % 60,
+        lat % 60,
+        if latlon.lat < 0 { "S" } else { "N" },
+        lon / 3600,
+        (lon / 60) % 60,
+        lon % 60,
+        if latlon.lon < 0 { "W" } else { "E" }
     )
 }
 
-// Openair distance format
-fn format_distance(distance: &str) -> String {
-    match distance.split_once(' ') {
-        Some((dist, unit)) => {
-            if unit == "km" {
-                format!("{:.3}", dist.parse::<f32>().unwrap() / 1.852)
-            } else {
-                dist.to_string()
-            }
-        }
-        _ => "".to_string(),
+// Openair distance format (nautical miles)
+pub(crate) fn format_distance(distance: &Distance) -> String {
+    let nm = format!("{:.3}", distance.nautical_miles());
+    let nm = nm.trim_end_matches('0').trim_end_matches('.');
+    if nm.is_empty() {
+        "0".to_string()
+    } else {
+        nm.to_string()
     }
 }
 
 // Give each volume a type
-fn airtype(feature: &Feature, volume: &Volume, settings: &Settings) -> AirType {
+pub fn airtype(feature: &Feature, volume: &Volume, settings: &Settings) -> AirType {
     let rules = feature
         .rules
         .iter()
@@ -164,11 +192,14 @@ fn airtype(feature: &Feature, volume: &Volume, settings: &Settings) -> AirType {
         .flatten()
         .collect::<HashSet<&Rule>>();
 
-    let comp = settings.format == Format::Competition;
+    let comp = settings.mode == Mode::Competition;
 
     if rules.contains(&Rule::Notam) {
         // NOTAM activated airspace
         AirType::ClassG
+    } else if let Some(airtype) = volume.airtype {
+        // Imported OpenAir type
+        airtype
     } else {
         match feature.icao_type {
             IcaoType::Atz => settings.atz,
@@ -244,7 +275,7 @@ fn airtype(feature: &Feature, volume: &Volume, settings: &Settings) -> AirType {
 }
 
 // Remove unwanted feature/volume
-fn airfilter(feature: &Feature, vol: &Volume, settings: &Settings) -> bool {
+pub fn airfilter(feature: &Feature, vol: &Volume, settings: &Settings) -> bool {
     let exclude = match feature.local_type {
         // No-ATZ
         Some(LocalType::NoAtz) => settings.unlicensed.is_none(),
@@ -274,12 +305,12 @@ fn airfilter(feature: &Feature, vol: &Volume, settings: &Settings) -> bool {
         _ => false,
     };
 
-    !(exclude || (norm_level(&vol.lower) >= settings.max_level))
+    !(exclude || (vol.lower >= Level::Fl(settings.max_level)))
 }
 
 // Give each volume a name
-fn do_name(feature: &Feature, vol: &Volume, n: usize, settings: &Settings) -> String {
-    let name = if let Some(name) = &vol.name {
+pub fn volume_name(feature: &Feature, vol: &Volume, n: usize, settings: &Settings) -> String {
+    if let Some(name) = &vol.name {
         name.clone()
     } else {
         let mut name = feature.name.clone();
@@ -310,7 +341,7 @@ fn do_name(feature: &Feature, vol: &Volume, n: usize, settings: &Settings) -> St
         }
 
         // Optional sequence number
-        if settings.format == Format::Competition && feature.geometry.len() > 1 {
+        if settings.mode == Mode::Competition && feature.geometry.len() > 1 {
             name.push('-');
             if let Some(seq) = &vol.seq {
                 name += seq;
@@ -342,36 +373,36 @@ fn do_name(feature: &Feature, vol: &Volume, n: usize, settings: &Settings) -> St
         };
 
         name
-    };
+    }
+}
 
-    format!("AN {}\n", name)
+fn do_name(feature: &Feature, vol: &Volume, n: usize, settings: &Settings) -> String {
+    format!("AN {}\n", volume_name(feature, vol, n, settings))
 }
 
 fn do_type(airtype: AirType) -> String {
     format!("AC {}\n", airtype.as_str())
 }
 
+fn do_style(pen: &Pen) -> String {
+    let (r, g, b) = pen.rgb();
+    format!("SP 0,{},{},{},{}\nSB -1,-1,-1\n", pen.width, r, g, b)
+}
+
 fn do_levels(volume: &Volume) -> String {
-    format!(
-        "AL {}\nAH {}\n",
-        format_level(&volume.lower),
-        format_level(&volume.upper)
-    )
+    format!("AL {}\nAH {}\n", volume.lower, volume.upper)
 }
 
 fn do_freq(freq: f64) -> String {
     format!("AF {:.3}\n", freq)
 }
 
-fn do_point(point: &str) -> String {
+fn do_point(point: &LatLon) -> String {
     format!("DP {}\n", format_latlon(point))
 }
 
-fn do_line(line: &[String]) -> String {
-    line.iter()
-        .map(|x| do_point(x))
-        .collect::<Vec<String>>()
-        .join("")
+fn do_line(line: &[LatLon]) -> String {
+    line.iter().map(do_point).collect::<Vec<String>>().join("")
 }
 
 fn do_circle(circle: &Circle) -> String {
@@ -382,7 +413,7 @@ fn do_circle(circle: &Circle) -> String {
     )
 }
 
-fn do_arc(arc: &Arc, from: &str, resolution: Option<u32>) -> String {
+fn do_arc(arc: &Arc, from: &LatLon, resolution: Option<u32>) -> String {
     match resolution {
         None => {
             let dir = if arc.dir == "cw" { "+" } else { "-" };
@@ -400,134 +431,78 @@ fn do_arc(arc: &Arc, from: &str, resolution: Option<u32>) -> String {
 
 fn do_boundary(boundary: &[Boundary], resolution: Option<u32>) -> String {
     let mut out = String::new();
-    let mut prev = "";
+    let mut prev = None;
 
     for segment in boundary {
         match segment {
             Boundary::Line(line) => {
                 out.push_str(&do_line(line));
-                prev = line.last().unwrap();
+                prev = line.last();
             }
             Boundary::Arc(arc) => {
-                out.push_str(&do_arc(arc, prev, resolution));
-                prev = &arc.to;
+                // Boundaries never start with an arc
+                if let Some(from) = prev {
+                    out.push_str(&do_arc(arc, from, resolution));
+                }
+                prev = Some(&arc.to);
             }
             Boundary::Circle(circle) => out.push_str(&do_circle(circle)),
         }
     }
 
     // Close the polygon
-    if let Boundary::Line(line) = &boundary[0] {
-        if line[0] != prev {
-            out.push_str(&do_point(&line[0]));
-        }
-    }
-
-    out
-}
-
-fn poly_arc(arc: &Arc, from: &str, resolution: u32) -> String {
-    let (clat, clon) = latlon_to_degrees(&arc.centre);
-    let centre = Point::new(clon, clat);
-
-    let (from_lat, from_lon) = latlon_to_degrees(from);
-    let from = Point::new(from_lon, from_lat);
-
-    let (to_lat, to_lon) = latlon_to_degrees(&arc.to);
-    let to = Point::new(to_lon, to_lat);
-
-    let mut from_ang = Geodesic::bearing(centre, from);
-    let mut to_ang = Geodesic::bearing(centre, to);
-
-    let radius = radius_to_metres(&arc.radius);
-
-    let resolution = (f64::from(resolution) * (radius / 4000.).sqrt()).round() as u32;
-
-    if arc.dir == "cw" {
-        if from_ang > to_ang {
-            to_ang += 360.;
-        }
-    } else {
-        if from_ang < to_ang {
-            from_ang += 360.;
+    if let Some(Boundary::Line(line)) = boundary.first() {
+        if let Some(first) = line.first() {
+            if Some(first) != prev {
+                out.push_str(&do_point(first));
+            }
         }
     }
 
-    let mut ang_array = (0..(resolution * 2) + 1)
-        .into_iter()
-        .map(|a| f64::from(a * 360) / f64::from(resolution))
-        .filter(|a| {
-            f64::from(*a) > (from_ang.min(to_ang) + 0.5)
-                && f64::from(*a) < (from_ang.max(to_ang) - 0.5)
-        })
-        .collect::<Vec<f64>>();
-
-    if arc.dir == "ccw" {
-        ang_array.reverse();
-    }
-
-    let mut out = ang_array
-        .into_iter()
-        .map(|a| {
-            let dest = Geodesic::destination(centre, a, radius);
-            degrees_to_point(dest.y(), dest.x())
-        })
-        .collect::<String>();
-
-    out.push_str(&degrees_to_point(to_lat, to_lon));
-
     out
 }
 
-fn degrees_to_dms(degrees: f64) -> (u32, u32, u32) {
-    let mut sec = (degrees * 3600.0).round() as u32;
-    let mut min = sec / 60;
-    sec = sec % 60;
-    let deg = min / 60;
-    min = min % 60;
-
-    (deg, min, sec)
-}
-
-fn degrees_to_point(lat: f64, lon: f64) -> String {
-    let lat_ns = if lat >= 0.0 { "N" } else { "S" };
-    let lon_ew = if lon >= 0.0 { "E" } else { "W" };
-
-    let (lat_deg, lat_min, lat_sec) = degrees_to_dms(lat.abs());
-    let (lon_deg, lon_min, lon_sec) = degrees_to_dms(lon.abs());
+// Arc as points, resolution is points per circle for a 4km radius
+fn poly_arc(arc: &Arc, from: &LatLon, resolution: u32) -> String {
+    let radius = arc.radius.metres();
+    let points = (f64::from(resolution) * (radius / 4000.).sqrt()).max(1.0);
 
-    format!(
-        "DP {:02}:{:02}:{:02} {} {:03}:{:02}:{:02} {}\n",
-        lat_deg, lat_min, lat_sec, lat_ns, lon_deg, lon_min, lon_sec, lon_ew
-    )
+    arc_points(arc, from, 360.0 / points)
+        .iter()
+        .map(|c| do_point(&LatLon::from_degrees(c.y, c.x)))
+        .collect()
 }
 
 // Merge radio frequency data
 fn merge_services(airspace: &mut Vec<Feature>, services: &Vec<Service>) {
-    // Create frequency map
-    let mut frequencies = HashMap::new();
+    // Create service map
+    let mut service_map = HashMap::new();
     for service in services {
         for id in &service.controls {
-            frequencies.insert(id, service.frequency);
+            service_map.insert(id, service);
         }
     }
 
-    // Add frequency properties
+    // Add frequency and callsign properties. Volumes without a service
+    // keep their own, e.g. from user OpenAir data
     for feature in airspace {
         for volume in &mut feature.geometry {
-            let volume_freq = if let Some(id) = &volume.id {
-                frequencies.get(&id)
+            let volume_service = if let Some(id) = &volume.id {
+                service_map.get(&id)
             } else {
                 None
             };
 
-            let feature_freq = if let Some(id) = &feature.id {
-                frequencies.get(&id)
+            let feature_service = if let Some(id) = &feature.id {
+                service_map.get(&id)
             } else {
                 None
             };
 
-            volume.frequency = volume_freq.or(feature_freq).cloned();
+            if let Some(service) = volume_service.or(feature_service) {
+                volume.frequency = Some(service.frequency);
+                volume.callsign = Some(service.callsign.clone());
+            }
         }
     }
 }
@@ -599,14 +574,16 @@ fn add_obstacles(airspace: &mut Vec<Feature>, obstacles: &Vec<Obstacle>) {
             local_type: Some(LocalType::Obstacle),
             rules: None,
             geometry: vec![Volume {
-                upper: obstacle.elevation.clone(),
-                lower: "SFC".to_string(),
+                upper: obstacle.elevation,
+                lower: Level::Sfc,
                 boundary: vec![Boundary::Circle(Circle {
-                    centre: obstacle.position.clone(),
-                    radius: "0.5 nm".to_string(),
+                    centre: obstacle.position,
+                    radius: Distance::nm(0.5),
                 })],
                 icao_class: None,
                 frequency: None,
+                callsign: None,
+                airtype: None,
                 id: None,
                 name: None,
                 rules: None,
@@ -618,7 +595,13 @@ fn add_obstacles(airspace: &mut Vec<Feature>, obstacles: &Vec<Obstacle>) {
 }
 
 // File header
-fn header(note: &str, airac: &str, commit: &str, user_agent: &str, settings: &Settings) -> String {
+pub(crate) fn header(
+    note: &str,
+    airac: &str,
+    commit: &str,
+    user_agent: &str,
+    settings: &Settings,
+) -> String {
     let mut hdr = "UK Airspace\n\
         Alan Sparrow (airspace@asselect.uk)\n\
         \n\
@@ -635,7 +618,7 @@ fn header(note: &str, airac: &str, commit: &str, user_agent: &str, settings: &Se
     .to_string();
 
     hdr.push_str(note);
-    hdr.push_str(&format!("\nAIRAC: {}\n", &airac[..10]));
+    hdr.push_str(&format!("\nAIRAC: {}\n", airac.get(..10).unwrap_or(airac)));
     hdr.push_str(&format!("Commit: {}\n", commit));
     hdr.push_str(&format!("Produced: {}\n", Utc::now().to_rfc3339()));
     hdr.push_str(&format!("User agent: {}\n", user_agent));
@@ -655,11 +638,11 @@ fn header(note: &str, airac: &str, commit: &str, user_agent: &str, settings: &Se
         + "\n"
 }
 
-// Generate OpenAir data
-pub fn openair(yaixm: &Yaixm, settings: &Settings, user_agent: &str) -> String {
+// Airspace features (including LOAs, RA(T)s and obstacles) for settings
+pub fn select_airspace(yaixm: &Yaixm, settings: &Settings) -> Vec<Feature> {
     let mut airspace = yaixm.airspace.clone();
 
-    if settings.format == Format::RatOnly {
+    if settings.mode == Mode::RatOnly {
         airspace = yaixm
             .rat
             .clone()
@@ -694,6 +677,87 @@ pub fn openair(yaixm: &Yaixm, settings: &Settings, user_agent: &str) -> String {
         merge_services(&mut airspace, &yaixm.service);
     }
 
+    airspace
+}
+
+// Volume selected for output
+pub struct SelectedVolume<'a> {
+    pub feature: &'a Feature,
+    pub volume: &'a Volume,
+    // Index of volume in feature geometry
+    pub index: usize,
+    pub airtype: AirType,
+}
+
+impl SelectedVolume<'_> {
+    pub fn name(&self, settings: &Settings) -> String {
+        volume_name(self.feature, self.volume, self.index, settings)
+    }
+
+    // Airspace class, from type or volume/feature class
+    pub fn icao_class(&self) -> Option<IcaoClass> {
+        match self.airtype {
+            AirType::ClassA => Some(IcaoClass::A),
+            AirType::ClassB => Some(IcaoClass::B),
+            AirType::ClassC => Some(IcaoClass::C),
+            AirType::ClassD => Some(IcaoClass::D),
+            AirType::ClassE => Some(IcaoClass::E),
+            AirType::ClassF => Some(IcaoClass::F),
+            AirType::ClassG => Some(IcaoClass::G),
+            _ => self.volume.icao_class.or(self.feature.icao_class),
+        }
+    }
+
+    // Unique id, from volume or feature id
+    pub fn id(&self) -> Option<String> {
+        match (&self.volume.id, &self.feature.id) {
+            (Some(id), _) => Some(id.clone()),
+            (None, Some(id)) if self.feature.geometry.len() == 1 => Some(id.clone()),
+            (None, Some(id)) => Some(format!("{}-{}", id, self.index + 1)),
+            (None, None) => None,
+        }
+    }
+
+    // Combined feature and volume rules
+    pub fn rules(&self) -> Vec<&'static str> {
+        let mut rules = self
+            .feature
+            .rules
+            .iter()
+            .chain(self.volume.rules.iter())
+            .flatten()
+            .map(Rule::as_str)
+            .collect::<Vec<&str>>();
+        rules.sort();
+        rules.dedup();
+        rules
+    }
+}
+
+// Filtered and classified volumes from selected airspace
+pub fn select_volumes<'a>(
+    airspace: &'a [Feature],
+    settings: &'a Settings,
+) -> impl Iterator<Item = SelectedVolume<'a>> {
+    airspace.iter().flat_map(move |feature| {
+        feature
+            .geometry
+            .iter()
+            .enumerate()
+            .filter(move |(_, volume)| airfilter(feature, volume, settings))
+            .map(move |(index, volume)| SelectedVolume {
+                feature,
+                volume,
+                index,
+                airtype: airtype(feature, volume, settings),
+            })
+    })
+}
+
+// Generate OpenAir data
+pub fn openair(yaixm: &Yaixm, settings: &Settings, user_agent: &str) -> String {
+    let airspace = select_airspace(yaixm, settings);
+
     // Build OpenAir data
     let rel = &yaixm.release;
     let mut output = header(
@@ -703,25 +767,631 @@ pub fn openair(yaixm: &Yaixm, settings: &Settings, user_agent: &str) -> String {
         user_agent,
         settings,
     );
-    for feature in airspace {
-        for (n, volume) in feature.geometry.iter().enumerate() {
-            let atype = airtype(&feature, &volume, &settings);
-            let res = if settings.format == Format::Competition {
-                Some(resolution(atype))
-            } else {
-                None
-            };
-            if airfilter(&feature, volume, settings) {
-                output.push_str("*\n");
-                output.push_str(&do_type(atype));
-                output.push_str(&do_name(&feature, volume, n, settings));
-                if let Some(freq) = volume.frequency {
-                    output.push_str(&do_freq(freq));
-                }
-                output.push_str(&do_levels(volume));
-                output.push_str(&do_boundary(&volume.boundary, res));
+    for sel in select_volumes(&airspace, settings) {
+        let res = if settings.mode == Mode::Competition {
+            Some(resolution(sel.airtype))
+        } else {
+            None
+        };
+        output.push_str("*\n");
+        if settings.format == Format::OpenAirExtended {
+            output.push_str(&format!("AC {}\n", extended_class(&sel)));
+            output.push_str(&format!("AY {}\n", extended_type(&sel)));
+        } else {
+            output.push_str(&do_type(sel.airtype));
+        }
+        output.push_str(&do_name(sel.feature, sel.volume, sel.index, settings));
+        if settings.styles {
+            output.push_str(&do_style(&settings.pen(sel.airtype)));
+        }
+        if settings.format == Format::OpenAirExtended {
+            if let Some(id) = sel.id() {
+                output.push_str(&format!("AI {}\n", id));
+            }
+        }
+        if let Some(freq) = sel.volume.frequency {
+            output.push_str(&do_freq(freq));
+        }
+        if settings.format == Format::OpenAirExtended {
+            if let Some(callsign) = &sel.volume.callsign {
+                output.push_str(&format!("AG {}\n", callsign));
+            }
+            // Activation times aren't known for NOTAM activated airspace
+            if sel.rules().contains(&Rule::Notam.as_str()) {
+                output.push_str("AA NONE/NONE\n");
             }
         }
+        output.push_str(&do_levels(sel.volume));
+        output.push_str(&do_boundary(&sel.volume.boundary, res));
     }
     output
 }
+
+// OpenAir coordinate, e.g. 51:23:45 N 001:23:45 W or 51:23.75N 001:23.75W
+fn parse_openair_latlon(text: &str) -> Result<LatLon, String> {
+    let text = text.to_ascii_uppercase();
+    let err = || format!("invalid coordinate \"{}\"", text.trim());
+
+    let dms = |s: &str| -> Option<f64> {
+        s.trim()
+            .split(':')
+            .map(|x| x.trim().parse::<f64>().ok())
+            .collect::<Option<Vec<f64>>>()
+            .filter(|parts| !parts.is_empty() && parts.len() <= 3)
+            .map(|parts| {
+                parts
+                    .iter()
+                    .zip([1.0, 60.0, 3600.0])
+                    .map(|(x, div)| x / div)
+                    .sum()
+            })
+    };
+
+    let lat_end = text.find(['N', 'S']).ok_or_else(err)?;
+    let lon_end = text.find(['E', 'W']).ok_or_else(err)?;
+    if lon_end < lat_end {
+        return Err(err());
+    }
+
+    let lat = dms(&text[..lat_end]).ok_or_else(err)?;
+    let lon = dms(&text[lat_end + 1..lon_end]).ok_or_else(err)?;
+    let lat = if &text[lat_end..=lat_end] == "S" {
+        -lat
+    } else {
+        lat
+    };
+    let lon = if &text[lon_end..=lon_end] == "W" {
+        -lon
+    } else {
+        lon
+    };
+    Ok(LatLon::from_degrees(lat, lon))
+}
+
+// OpenAir level, e.g. SFC, FL65, 2000ft, 2000 ft AMSL, 1500 ft AGL
+fn parse_openair_level(text: &str) -> Result<Level, String> {
+    let upper = text.trim().to_ascii_uppercase();
+    match upper.as_str() {
+        "SFC" | "GND" | "0" => return Ok(Level::Sfc),
+        "UNL" | "UNLIM" | "UNLTD" | "UNLIMITED" => return Ok(Level::Unlimited),
+        _ => (),
+    }
+
+    if let Some(fl) = upper.strip_prefix("FL") {
+        return fl
+            .trim()
+            .parse::<u16>()
+            .map(Level::Fl)
+            .map_err(|_| format!("invalid level \"{}\"", text));
+    }
+
+    let digits = upper
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(upper.len());
+    let value: u32 = upper[..digits]
+        .parse()
+        .map_err(|_| format!("invalid level \"{}\"", text))?;
+    let rest = upper[digits..].replace(' ', "");
+
+    let (metres, rest) = match rest.strip_prefix('M') {
+        Some(r) if !r.starts_with("SL") => (true, r),
+        _ => (false, rest.trim_start_matches("FT").trim_start_matches('F')),
+    };
+    let feet = if metres {
+        (f64::from(value) / 0.3048).round() as u32
+    } else {
+        value
+    };
+
+    match rest {
+        "" | "MSL" | "AMSL" | "ALT" => Ok(Level::Altitude(feet)),
+        "AGL" | "AGND" | "SFC" | "GND" if feet == 0 => Ok(Level::Sfc),
+        "AGL" | "AGND" | "SFC" | "GND" => Ok(Level::Height(feet)),
+        _ => Err(format!("invalid level \"{}\"", text)),
+    }
+}
+
+// Feature from OpenAir class and name, with type suffix removed from name
+fn openair_feature(class: &str, name: &str) -> Feature {
+    let mut feature = Feature {
+        id: None,
+        name: name.to_string(),
+        icao_type: IcaoType::Other,
+        local_type: None,
+        icao_class: None,
+        rules: None,
+        geometry: vec![],
+    };
+
+    let mut strip = |suffix: &str| match name.strip_suffix(suffix) {
+        Some(n) => {
+            feature.name = n.trim_end().to_string();
+            true
+        }
+        None => false,
+    };
+
+    let (icao_type, local_type, icao_class) = match class {
+        "A" => (IcaoType::Cta, None, Some(IcaoClass::A)),
+        "B" => (IcaoType::Cta, None, Some(IcaoClass::B)),
+        "C" => (IcaoType::Cta, None, Some(IcaoClass::C)),
+        "D" => (IcaoType::Cta, None, Some(IcaoClass::D)),
+        "E" => (IcaoType::Cta, None, Some(IcaoClass::E)),
+        "F" => (IcaoType::Cta, None, Some(IcaoClass::F)),
+        "G" => (IcaoType::Cta, None, Some(IcaoClass::G)),
+        "CTR" | "CTA" if strip(" ATZ") => (IcaoType::Atz, None, None),
+        "CTR" => (IcaoType::Ctr, None, None),
+        "CTA" => (IcaoType::Cta, None, None),
+        "P" | "GP" => (IcaoType::P, None, None),
+        "R" => (IcaoType::R, None, None),
+        "Q" => (IcaoType::D, None, None),
+        "W" | "GSEC" => (IcaoType::Other, None, None),
+        "MATZ" => {
+            strip(" MATZ");
+            (IcaoType::Other, Some(LocalType::Matz), None)
+        }
+        "RMZ" => (IcaoType::Other, Some(LocalType::Rmz), None),
+        "TMZ" => (IcaoType::Other, Some(LocalType::Tmz), None),
+        _ => (IcaoType::Other, None, None),
+    };
+
+    feature.icao_type = icao_type;
+    feature.local_type = local_type;
+    feature.icao_class = icao_class;
+    feature
+}
+
+// OpenAir record
+#[derive(Debug, PartialEq)]
+pub(crate) enum OpenAirRecord<'a> {
+    Class(&'a str),
+    Name(&'a str),
+    Lower(Level),
+    Upper(Level),
+    Frequency(f64),
+    Callsign(&'a str),
+    Id(&'a str),
+    Centre(LatLon),
+    Clockwise(bool),
+    Point(LatLon),
+    // Radius (nm)
+    Circle(f64),
+    ArcPoints(LatLon, LatLon),
+    // Radius (nm), start and end bearings
+    ArcAngles(f64, f64, f64),
+    Airway,
+    // Styling, other variables and unknown records
+    Other,
+}
+
+// Record name and argument, None for blank and comment lines
+pub(crate) fn openair_line(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('*') {
+        return None;
+    }
+
+    let (record, arg) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    Some((record, arg.split('*').next().unwrap_or_default().trim()))
+}
+
+pub(crate) fn openair_record<'a>(record: &str, arg: &'a str) -> Result<OpenAirRecord<'a>, String> {
+    Ok(match record {
+        "AC" => OpenAirRecord::Class(arg),
+        "AN" => OpenAirRecord::Name(arg),
+        "AL" => OpenAirRecord::Lower(parse_openair_level(arg)?),
+        "AH" => OpenAirRecord::Upper(parse_openair_level(arg)?),
+        "AF" => OpenAirRecord::Frequency(
+            arg.parse::<f64>()
+                .ok()
+                .filter(|f| f.is_finite() && *f > 0.0)
+                .ok_or(format!("invalid frequency \"{}\"", arg))?,
+        ),
+        "AG" if arg.is_empty() => return Err("missing callsign".to_string()),
+        "AG" => OpenAirRecord::Callsign(arg),
+        "AI" => OpenAirRecord::Id(arg),
+        "V" => {
+            // Variables, e.g. X=52:00:00 N 001:00:00 W or D = -
+            let (var, value) = arg.split_once('=').unwrap_or((arg, ""));
+            match var.trim() {
+                "X" => OpenAirRecord::Centre(parse_openair_latlon(value)?),
+                "D" => OpenAirRecord::Clockwise(value.trim() != "-"),
+                _ => OpenAirRecord::Other,
+            }
+        }
+        "DP" => OpenAirRecord::Point(parse_openair_latlon(arg)?),
+        "DC" => OpenAirRecord::Circle(
+            arg.parse()
+                .map_err(|_| format!("invalid radius \"{}\"", arg))?,
+        ),
+        "DB" => {
+            let (from, to) = arg
+                .split_once(',')
+                .ok_or(format!("invalid arc \"{}\"", arg))?;
+            OpenAirRecord::ArcPoints(parse_openair_latlon(from)?, parse_openair_latlon(to)?)
+        }
+        "DA" => {
+            let values = arg
+                .split(',')
+                .map(|x| x.trim().parse::<f64>().ok())
+                .collect::<Option<Vec<f64>>>()
+                .filter(|v| v.len() == 3)
+                .ok_or(format!("invalid arc \"{}\"", arg))?;
+            OpenAirRecord::ArcAngles(values[0], values[1], values[2])
+        }
+        "DY" => OpenAirRecord::Airway,
+        _ => OpenAirRecord::Other,
+    })
+}
+
+// Arc from DB record end points
+pub(crate) fn openair_arc(centre: LatLon, from: LatLon, to: LatLon, cw: bool) -> Arc {
+    let radius = Geodesic::distance(centre.point(), from.point());
+    Arc {
+        centre,
+        dir: if cw { "cw" } else { "ccw" }.to_string(),
+        radius: Distance::nm(radius / Distance::nm(1.0).metres()),
+        to,
+    }
+}
+
+// DA record end points, from radius (nm) and bearings
+pub(crate) fn openair_arc_ends(
+    centre: LatLon,
+    radius: f64,
+    start: f64,
+    end: f64,
+) -> (LatLon, LatLon) {
+    let radius = Distance::nm(radius).metres();
+    let point = |bearing| {
+        let p = Geodesic::destination(centre.point(), bearing, radius);
+        LatLon::from_degrees(p.y(), p.x())
+    };
+    (point(start), point(end))
+}
+
+// Parse OpenAir data into features, one per airspace
+pub fn parse_openair(text: &str) -> Result<Vec<Feature>, String> {
+    let mut features = vec![];
+    // Class, name and volume of current airspace
+    let mut current: Option<(String, String, Volume)> = None;
+
+    // Arc/circle centre and direction
+    let mut centre = None;
+    let mut clockwise = true;
+
+    for (n, line) in text.lines().enumerate() {
+        let Some((record, arg)) = openair_line(line) else {
+            continue;
+        };
+        let err = |e: String| format!("line {}: {}", n + 1, e);
+
+        if record == "AC" {
+            if let Some(airspace) = current.take() {
+                features.push(finish_openair(airspace).map_err(err)?);
+            }
+            centre = None;
+            clockwise = true;
+            current = Some((
+                arg.to_string(),
+                String::new(),
+                Volume {
+                    id: None,
+                    name: None,
+                    lower: Level::Sfc,
+                    upper: Level::Unlimited,
+                    icao_class: None,
+                    rules: None,
+                    seq: None,
+                    frequency: None,
+                    callsign: None,
+                    airtype: None,
+                    boundary: vec![],
+                },
+            ));
+            continue;
+        }
+
+        let Some((_, name, volume)) = current.as_mut() else {
+            // Records before first AC are ignored
+            continue;
+        };
+
+        match openair_record(record, arg).map_err(err)? {
+            OpenAirRecord::Name(arg) => *name = arg.to_string(),
+            OpenAirRecord::Lower(level) => volume.lower = level,
+            OpenAirRecord::Upper(level) => volume.upper = level,
+            OpenAirRecord::Frequency(freq) => volume.frequency = Some(freq),
+            OpenAirRecord::Callsign(arg) => volume.callsign = Some(arg.to_string()),
+            OpenAirRecord::Id(arg) => volume.id = Some(arg.to_string()),
+            OpenAirRecord::Centre(latlon) => centre = Some(latlon),
+            OpenAirRecord::Clockwise(cw) => clockwise = cw,
+            OpenAirRecord::Point(point) => push_point(&mut volume.boundary, point),
+            OpenAirRecord::Circle(radius) => {
+                let centre = centre.ok_or_else(|| err("circle without centre".to_string()))?;
+                volume.boundary.push(Boundary::Circle(Circle {
+                    centre,
+                    radius: Distance::nm(radius),
+                }));
+            }
+            OpenAirRecord::ArcPoints(from, to) => {
+                let centre = centre.ok_or_else(|| err("arc without centre".to_string()))?;
+                push_arc(&mut volume.boundary, centre, from, to, clockwise);
+            }
+            OpenAirRecord::ArcAngles(radius, start, end) => {
+                let centre = centre.ok_or_else(|| err("arc without centre".to_string()))?;
+                let (from, to) = openair_arc_ends(centre, radius, start, end);
+                push_arc(&mut volume.boundary, centre, from, to, clockwise);
+            }
+            OpenAirRecord::Airway => {
+                return Err(err("airway records are not supported".to_string()))
+            }
+            // Styling and other extended records are ignored
+            OpenAirRecord::Class(_) | OpenAirRecord::Other => (),
+        }
+    }
+
+    if let Some(airspace) = current.take() {
+        features.push(finish_openair(airspace)?);
+    }
+    Ok(features)
+}
+
+fn push_point(boundary: &mut Vec<Boundary>, point: LatLon) {
+    if let Some(Boundary::Line(line)) = boundary.last_mut() {
+        if line.last() != Some(&point) {
+            line.push(point);
+        }
+    } else {
+        boundary.push(Boundary::Line(vec![point]));
+    }
+}
+
+fn push_arc(boundary: &mut Vec<Boundary>, centre: LatLon, from: LatLon, to: LatLon, cw: bool) {
+    push_point(boundary, from);
+    boundary.push(Boundary::Arc(openair_arc(centre, from, to, cw)));
+}
+
+// Complete feature, type from class and name
+fn finish_openair((class, name, mut volume): (String, String, Volume)) -> Result<Feature, String> {
+    let feature = openair_feature(&class, &name);
+    if volume.boundary.is_empty() {
+        return Err(format!("{}: no boundary", feature.name));
+    }
+
+    // Closing point is implicit in YAIXM lines
+    let first = match volume.boundary.first() {
+        Some(Boundary::Line(line)) => line.first().copied(),
+        _ => None,
+    };
+    let n = volume.boundary.len();
+    if let Some(Boundary::Line(line)) = volume.boundary.last_mut() {
+        if (line.len() > 1 || n > 1) && line.last().copied() == first {
+            line.pop();
+            if line.is_empty() {
+                volume.boundary.pop();
+            }
+        }
+    }
+
+    // Types with no YAIXM class or local type equivalent
+    volume.airtype = match (class.as_str(), &feature.icao_type) {
+        ("W" | "GSEC", _) => Some(AirType::Gliding),
+        (_, IcaoType::Ctr) => Some(AirType::Ctr),
+        (_, IcaoType::Cta) if feature.icao_class.is_none() => Some(AirType::Cta),
+        _ => None,
+    };
+
+    Ok(Feature {
+        geometry: vec![volume],
+        ..feature
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn openair_distance() {
+        assert_eq!(format_distance(&Distance::nm(2.5)), "2.5");
+        assert_eq!(format_distance(&Distance::nm(10.0)), "10");
+        assert_eq!(format_distance(&"926 m".parse().unwrap()), "0.5");
+        assert_eq!(format_distance(&Distance::nm(0.0)), "0");
+    }
+
+    #[test]
+    fn openair_latlon() {
+        let latlon = "512345N 0012345W".parse().unwrap();
+        assert_eq!(format_latlon(&latlon), "51:23:45 N 001:23:45 W");
+    }
+
+    #[test]
+    fn openair_poly_arc() {
+        let arc = Arc {
+            centre: "520000N 0010000W".parse().unwrap(),
+            dir: "cw".to_string(),
+            radius: Distance::nm(5.0),
+            to: "520000N 0005153W".parse().unwrap(),
+        };
+        let out = poly_arc(&arc, &"520500N 0010000W".parse().unwrap(), 72);
+        let points = out.lines().collect::<Vec<&str>>();
+
+        // 72 points per circle at 4km radius, about 110 at 5nm, so 28
+        // for a quadrant
+        assert_eq!(points.len(), 28);
+        assert!(points.iter().all(|p| p.starts_with("DP ")));
+        assert_eq!(*points.last().unwrap(), "DP 52:00:00 N 000:51:53 W");
+    }
+
+    #[test]
+    fn openair_extended() {
+        let yaixm = crate::yaixm::from_json(include_str!("../tests/data/square.json")).unwrap();
+        let settings = Settings {
+            format: Format::OpenAirExtended,
+            ..Default::default()
+        };
+        let output = openair(&yaixm, &settings, "");
+        let records = output
+            .lines()
+            .filter(|line| !line.starts_with('*'))
+            .collect::<Vec<&str>>();
+
+        assert_eq!(records[..4], ["AC D", "AY CTR", "AN SQUARE", "AI square-1"]);
+        assert!(records.contains(&"AG SQUARE RADAR"));
+        assert!(!records.iter().any(|r| r.starts_with("AA")));
+
+        // NOTAM activated airspace
+        let mut json: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/data/square.json")).unwrap();
+        json["airspace"][0]["rules"] = serde_json::json!(["NOTAM"]);
+        let yaixm = crate::yaixm::from_json(&json.to_string()).unwrap();
+        let output = openair(&yaixm, &settings, "");
+        assert!(output.contains("AG SQUARE RADAR\nAA NONE/NONE\nAL SFC\n"));
+    }
+
+    #[test]
+    fn openair_styles() {
+        let yaixm = crate::yaixm::from_json(include_str!("../tests/data/square.json")).unwrap();
+        let mut settings = Settings::default();
+        assert!(!openair(&yaixm, &settings, "").contains("\nSP "));
+
+        settings.styles = true;
+        let output = openair(&yaixm, &settings, "");
+        assert!(output.contains("AN SQUARE\nSP 0,2,31,119,180\nSB -1,-1,-1\n"));
+    }
+
+    fn read_one(text: &str) -> Volume {
+        let features = parse_openair(text).unwrap();
+        assert_eq!(features.len(), 1);
+        features[0].geometry[0].clone()
+    }
+
+    #[test]
+    fn reader_points() {
+        let features = parse_openair(
+            "* Comment\n\
+             AC R\n\
+             AN TEST\n\
+             AL SFC\n\
+             AH 2000ft\n\
+             DP 52:00:00 N 001:00:00 W\n\
+             DP 52:00:00 N 000:00:00 W * Inline comment\n\
+             DP 51:00:00 N 000:00:00 W\n\
+             DP 52:00:00 N 001:00:00 W\n",
+        )
+        .unwrap();
+
+        assert_eq!(features.len(), 1);
+        assert_eq!(features[0].name, "TEST");
+        assert_eq!(features[0].icao_type, IcaoType::R);
+        let volume = &features[0].geometry[0];
+        assert_eq!(volume.lower, Level::Sfc);
+        assert_eq!(volume.upper, Level::Altitude(2000));
+
+        // Closing point is dropped
+        assert_eq!(
+            volume.boundary,
+            [Boundary::Line(vec![
+                LatLon::from_degrees(52.0, -1.0),
+                LatLon::from_degrees(52.0, 0.0),
+                LatLon::from_degrees(51.0, 0.0),
+            ])]
+        );
+    }
+
+    #[test]
+    fn reader_circle() {
+        let volume = read_one("AC D\nAN TEST\nV X=52:00:00 N 001:00:00 W\nDC 2.5\n");
+        assert_eq!(
+            volume.boundary,
+            [Boundary::Circle(Circle {
+                centre: LatLon::from_degrees(52.0, -1.0),
+                radius: Distance::nm(2.5),
+            })]
+        );
+    }
+
+    #[test]
+    fn reader_arcs() {
+        let centre = LatLon::from_degrees(52.0, -1.0);
+        // Five minutes of latitude is about 5 nm
+        let radius_ok = |arc: &Arc| (arc.radius.metres() - Distance::nm(5.0).metres()).abs() < 50.0;
+
+        // Anticlockwise arc between points
+        let volume = read_one(
+            "AC D\nAN TEST\n\
+             V X=52:00:00 N 001:00:00 W\n\
+             V D=-\n\
+             DB 52:05:00 N 001:00:00 W, 51:55:00 N 001:00:00 W\n",
+        );
+        let Boundary::Arc(arc) = &volume.boundary[1] else {
+            panic!("no arc");
+        };
+        assert_eq!(
+            volume.boundary[0],
+            Boundary::Line(vec![LatLon::from_degrees(52.0 + 5.0 / 60.0, -1.0)])
+        );
+        assert_eq!(arc.dir, "ccw");
+        assert_eq!(arc.centre, centre);
+        assert_eq!(arc.to, LatLon::from_degrees(52.0 - 5.0 / 60.0, -1.0));
+        assert!(radius_ok(arc));
+
+        // Clockwise arc from radius and bearings
+        let volume = read_one("AC D\nAN TEST\nV X=52:00:00 N 001:00:00 W\nDA 5,0,90\n");
+        let Boundary::Arc(arc) = &volume.boundary[1] else {
+            panic!("no arc");
+        };
+        let Boundary::Line(line) = &volume.boundary[0] else {
+            panic!("no start point");
+        };
+        assert_eq!(arc.dir, "cw");
+        assert!(radius_ok(arc));
+        assert_eq!(line[0].lon, centre.lon);
+        assert!(line[0].lat > centre.lat);
+        assert!(arc.to.lon > centre.lon);
+    }
+
+    #[test]
+    fn reader_frequency() {
+        let volume = read_one(
+            "AC D\nAN TEST\nAF 123.450\nAG TEST RADAR\n\
+             V X=52:00:00 N 001:00:00 W\nDC 2\n",
+        );
+        assert_eq!(volume.frequency, Some(123.45));
+        assert_eq!(volume.callsign.as_deref(), Some("TEST RADAR"));
+    }
+
+    #[test]
+    fn reader_errors() {
+        let error = |text: &str| parse_openair(text).err().unwrap();
+
+        assert_eq!(
+            error("AC D\nAN TEST\nAF 123,45\n"),
+            "line 3: invalid frequency \"123,45\""
+        );
+        assert_eq!(error("AC D\nAN TEST\nAG\n"), "line 3: missing callsign");
+        assert_eq!(
+            error("AC D\nAL 12 furlongs\n"),
+            "line 2: invalid level \"12 furlongs\""
+        );
+        assert_eq!(
+            error("AC D\nDP 52:00 001:00 W\n"),
+            "line 2: invalid coordinate \"52:00 001:00 W\""
+        );
+        assert_eq!(error("AC D\nDC 5\n"), "line 2: circle without centre");
+        assert_eq!(
+            error("AC D\nV X=52:00:00 N 001:00:00 W\nDB 52:05:00 N 001:00:00 W\n"),
+            "line 3: invalid arc \"52:05:00 N 001:00:00 W\""
+        );
+        assert_eq!(
+            error("AC D\nV X=52:00:00 N 001:00:00 W\nDA 5,0\n"),
+            "line 3: invalid arc \"5,0\""
+        );
+        assert_eq!(
+            error("AC D\nDY 52:00:00 N 001:00:00 W\n"),
+            "line 2: airway records are not supported"
+        );
+        assert_eq!(error("AC D\nAN TEST\nAL SFC\n"), "TEST: no boundary");
+    }
+}
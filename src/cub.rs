@@ -0,0 +1,288 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::convert::{select_airspace, select_volumes, SelectedVolume};
+use crate::geometry::{volume_polygon, DEFAULT_TOLERANCE};
+use crate::settings::{AirType, Settings};
+use crate::yaixm::{IcaoClass, IcaoType, Level, Yaixm};
+
+// Naviter SeeYou CUB format. All values little endian, positions in
+// radians, altitudes in metres. Sizes, flags, altitude styles and type
+// values are those in Naviter's CUB file format description
+
+const IDENT: u32 = 0x425a4143;
+const HEADER_SIZE: usize = 210;
+const ITEM_SIZE: usize = 43;
+const POINT_SIZE: usize = 5;
+
+// Point list flags
+const FLAG_POINT: u8 = 0x01;
+const FLAG_NAME: u8 = 0x40;
+const FLAG_FREQUENCY: u8 = 0x41;
+
+// Altitude styles, lower nibble is the bottom, upper nibble the top
+const ALT_AGL: u8 = 1;
+const ALT_MSL: u8 = 2;
+const ALT_FL: u8 = 3;
+const ALT_UNL: u8 = 4;
+
+// Airspace types, upper nibble of item type
+const TYPE_UNKNOWN: u8 = 0x00;
+const TYPE_AIRWAY: u8 = 0x10;
+const TYPE_CTR: u8 = 0x20;
+const TYPE_CTA: u8 = 0x30;
+const TYPE_RESTRICTED: u8 = 0x40;
+const TYPE_PROHIBITED: u8 = 0x50;
+const TYPE_DANGER: u8 = 0x60;
+const TYPE_GLIDER: u8 = 0x70;
+const TYPE_MATZ: u8 = 0x80;
+const TYPE_TMZ: u8 = 0x90;
+const TYPE_RMZ: u8 = 0xa0;
+
+// Offsets are 16 bit, scaled to fit the largest item
+const SCALE_RANGE: f64 = 32000.0;
+
+const FT_TO_M: f64 = 0.3048;
+
+// Airspace type and class (lower nibble)
+fn cub_type(sel: &SelectedVolume) -> u8 {
+    let airtype = match sel.airtype {
+        AirType::Ctr => TYPE_CTR,
+        AirType::Cta => TYPE_CTA,
+        AirType::Restricted => TYPE_RESTRICTED,
+        AirType::Prohibited => TYPE_PROHIBITED,
+        AirType::Danger => TYPE_DANGER,
+        AirType::Gliding => TYPE_GLIDER,
+        AirType::Matz => TYPE_MATZ,
+        AirType::Tmz => TYPE_TMZ,
+        AirType::Rmz => TYPE_RMZ,
+        // Classified airspace, type from ICAO type
+        _ => match sel.feature.icao_type {
+            IcaoType::Awy => TYPE_AIRWAY,
+            IcaoType::Ctr => TYPE_CTR,
+            IcaoType::Cta | IcaoType::Tma => TYPE_CTA,
+            _ => TYPE_UNKNOWN,
+        },
+    };
+
+    let class = match sel.icao_class() {
+        Some(IcaoClass::A) => 1,
+        Some(IcaoClass::B) => 2,
+        Some(IcaoClass::C) => 3,
+        Some(IcaoClass::D) => 4,
+        Some(IcaoClass::E) => 5,
+        Some(IcaoClass::F) => 6,
+        Some(IcaoClass::G) => 7,
+        None => 0,
+    };
+
+    airtype | class
+}
+
+// Altitude style and value
+fn cub_level(level: &Level) -> (u8, i16) {
+    let style = match level {
+        Level::Sfc | Level::Height(_) => ALT_AGL,
+        Level::Altitude(_) => ALT_MSL,
+        Level::Fl(_) => ALT_FL,
+        Level::Unlimited => ALT_UNL,
+    };
+    let metres = level
+        .feet()
+        .map_or(0, |ft| (f64::from(ft) * FT_TO_M).round() as i16);
+    (style, metres)
+}
+
+// Volume outline in radians, without closing point
+struct Outline<'a> {
+    sel: SelectedVolume<'a>,
+    points: Vec<(f64, f64)>,
+    left: f64,
+    top: f64,
+    right: f64,
+    bottom: f64,
+}
+
+impl<'a> Outline<'a> {
+    fn new(sel: SelectedVolume<'a>) -> Self {
+        let polygon = volume_polygon(sel.volume, DEFAULT_TOLERANCE);
+        let mut points = polygon
+            .exterior()
+            .coords()
+            .map(|c| (c.x.to_radians(), c.y.to_radians()))
+            .collect::<Vec<(f64, f64)>>();
+        points.pop();
+
+        let left = points.iter().map(|p| p.0).fold(f64::MAX, f64::min);
+        let right = points.iter().map(|p| p.0).fold(f64::MIN, f64::max);
+        let bottom = points.iter().map(|p| p.1).fold(f64::MAX, f64::min);
+        let top = points.iter().map(|p| p.1).fold(f64::MIN, f64::max);
+
+        Outline {
+            sel,
+            points,
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+}
+
+fn put_u16(buf: &mut Vec<u8>, x: u16) {
+    buf.extend_from_slice(&x.to_le_bytes());
+}
+
+fn put_i16(buf: &mut Vec<u8>, x: i16) {
+    buf.extend_from_slice(&x.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, x: u32) {
+    buf.extend_from_slice(&x.to_le_bytes());
+}
+
+fn put_i32(buf: &mut Vec<u8>, x: i32) {
+    buf.extend_from_slice(&x.to_le_bytes());
+}
+
+fn put_f32(buf: &mut Vec<u8>, x: f64) {
+    buf.extend_from_slice(&(x as f32).to_le_bytes());
+}
+
+// Length prefixed string, truncated to 255 bytes
+fn put_str(buf: &mut Vec<u8>, s: &str) {
+    let bytes = &s.as_bytes()[..s.len().min(255)];
+    buf.push(bytes.len() as u8);
+    buf.extend_from_slice(bytes);
+}
+
+// Point list for volume, relative to bottom left of item
+fn points_data(outline: &Outline, scale: f32, settings: &Settings) -> Vec<u8> {
+    let scale = f64::from(scale);
+    let (left, bottom) = (
+        f64::from(outline.left as f32),
+        f64::from(outline.bottom as f32),
+    );
+
+    let mut data = vec![];
+    for (x, y) in &outline.points {
+        data.push(FLAG_POINT);
+        put_i16(&mut data, ((x - left) / scale).round() as i16);
+        put_i16(&mut data, ((y - bottom) / scale).round() as i16);
+    }
+
+    data.push(FLAG_NAME);
+    put_str(&mut data, &outline.sel.name(settings));
+
+    if let Some(freq) = outline.sel.volume.frequency {
+        data.push(FLAG_FREQUENCY);
+        put_u32(&mut data, (freq * 1e6).round() as u32);
+        put_str(&mut data, "");
+    }
+
+    data
+}
+
+// Generate SeeYou CUB data
+pub fn cub(yaixm: &Yaixm, settings: &Settings) -> Vec<u8> {
+    let airspace = select_airspace(yaixm, settings);
+
+    let outlines = select_volumes(&airspace, settings)
+        .map(Outline::new)
+        .filter(|o| !o.points.is_empty())
+        .collect::<Vec<Outline>>();
+
+    // Overall extent
+    let left = outlines.iter().map(|o| o.left).fold(f64::MAX, f64::min);
+    let right = outlines.iter().map(|o| o.right).fold(f64::MIN, f64::max);
+    let bottom = outlines.iter().map(|o| o.bottom).fold(f64::MAX, f64::min);
+    let top = outlines.iter().map(|o| o.top).fold(f64::MIN, f64::max);
+    let max_width = outlines
+        .iter()
+        .map(|o| o.right - o.left)
+        .fold(0.0, f64::max);
+    let max_height = outlines
+        .iter()
+        .map(|o| o.top - o.bottom)
+        .fold(0.0, f64::max);
+    let max_pts = outlines.iter().map(|o| o.points.len()).max().unwrap_or(0);
+    let scale = (max_width.max(max_height) / SCALE_RANGE).max(1e-9) as f32;
+
+    // Items and point data
+    let mut items = vec![];
+    let mut data = vec![];
+    for outline in &outlines {
+        let (lower_style, lower) = cub_level(&outline.sel.volume.lower);
+        let (upper_style, upper) = cub_level(&outline.sel.volume.upper);
+
+        put_f32(&mut items, outline.left);
+        put_f32(&mut items, outline.top);
+        put_f32(&mut items, outline.right);
+        put_f32(&mut items, outline.bottom);
+        items.push(cub_type(&outline.sel));
+        items.push(lower_style | (upper_style << 4));
+        put_i16(&mut items, lower);
+        put_i16(&mut items, upper);
+        put_i32(&mut items, data.len() as i32);
+        // Timeout, extra data, active time and extended type unused
+        put_i32(&mut items, 0);
+        put_u32(&mut items, 0);
+        items.extend_from_slice(&[0; 8]);
+        items.push(0);
+
+        data.extend(points_data(outline, scale, settings));
+    }
+
+    // Header
+    let mut buf = Vec::with_capacity(HEADER_SIZE + items.len() + data.len());
+    put_u32(&mut buf, IDENT);
+
+    let mut title = format!("UK Airspace AIRAC {}", yaixm.release.date()).into_bytes();
+    title.resize(112, 0);
+    buf.extend_from_slice(&title);
+
+    // Allowed serials
+    for _ in 0..8 {
+        put_u16(&mut buf, 0);
+    }
+    // PC byte order, not secured, no CRC or key
+    buf.push(1);
+    buf.push(0);
+    put_u32(&mut buf, 0);
+    buf.extend_from_slice(&[0; 16]);
+
+    put_i32(&mut buf, ITEM_SIZE as i32);
+    put_i32(&mut buf, POINT_SIZE as i32);
+    put_i32(&mut buf, outlines.len() as i32);
+    put_i32(&mut buf, max_pts as i32);
+    if outlines.is_empty() {
+        buf.extend_from_slice(&[0; 16]);
+    } else {
+        put_f32(&mut buf, left);
+        put_f32(&mut buf, top);
+        put_f32(&mut buf, right);
+        put_f32(&mut buf, bottom);
+    }
+    put_f32(&mut buf, max_width);
+    put_f32(&mut buf, max_height);
+    buf.extend_from_slice(&scale.to_le_bytes());
+    put_i32(&mut buf, HEADER_SIZE as i32);
+    put_i32(&mut buf, (HEADER_SIZE + items.len()) as i32);
+    put_i32(&mut buf, 0);
+
+    buf.extend(items);
+    buf.extend(data);
+    buf
+}
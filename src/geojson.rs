@@ -0,0 +1,115 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::convert::{select_airspace, select_volumes};
+use crate::geometry::{volume_polygon, DEFAULT_TOLERANCE};
+use crate::settings::Settings;
+use crate::yaixm::Yaixm;
+use geo::orient::{Direction, Orient};
+use serde_json::{json, Value};
+
+// Polygon coordinates, exterior ring anti-clockwise (RFC 7946)
+fn polygon_coords(polygon: &geo::Polygon<f64>) -> Value {
+    let ring = polygon
+        .orient(Direction::Default)
+        .exterior()
+        .coords()
+        .map(|c| json!([c.x, c.y]))
+        .collect::<Vec<Value>>();
+    json!([ring])
+}
+
+// Generate GeoJSON FeatureCollection
+pub fn geojson(yaixm: &Yaixm, settings: &Settings) -> String {
+    let airspace = select_airspace(yaixm, settings);
+
+    let features = select_volumes(&airspace, settings)
+        .map(|sel| {
+            json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": polygon_coords(&volume_polygon(sel.volume, DEFAULT_TOLERANCE)),
+                },
+                "properties": {
+                    "name": sel.name(settings),
+                    "type": sel.airtype.to_string(),
+                    "lower": sel.volume.lower.to_string(),
+                    "upper": sel.volume.upper.to_string(),
+                    "lower_ft": sel.volume.lower.feet(),
+                    "upper_ft": sel.volume.upper.feet(),
+                    "frequency": sel.volume.frequency,
+                    "feature_id": sel.feature.id,
+                    "volume_id": sel.volume.id,
+                    "rules": sel.rules(),
+                },
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    let rel = &yaixm.release;
+    json!({
+        "type": "FeatureCollection",
+        "airac_date": rel.airac_date,
+        "commit": rel.commit,
+        "features": features,
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaixm::from_json;
+
+    #[test]
+    fn square() {
+        let yaixm = from_json(include_str!("../tests/data/square.json")).unwrap();
+        let output: Value = serde_json::from_str(&geojson(&yaixm, &Settings::default())).unwrap();
+
+        assert_eq!(output["type"], "FeatureCollection");
+        let features = output["features"].as_array().unwrap();
+        assert_eq!(features.len(), 1);
+
+        let properties = &features[0]["properties"];
+        assert_eq!(properties["name"], "SQUARE");
+        assert_eq!(properties["volume_id"], "square-1");
+        assert_eq!(properties["upper_ft"], 2500);
+        assert_eq!(properties["frequency"], 123.45);
+
+        // Closed ring of the four corners
+        let ring = features[0]["geometry"]["coordinates"][0]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| (c[0].as_f64().unwrap(), c[1].as_f64().unwrap()))
+            .collect::<Vec<(f64, f64)>>();
+        assert_eq!(ring.len(), 5);
+        assert_eq!(ring.first(), ring.last());
+        for corner in [(-1.0, 51.0), (-1.0, 51.0 + 1.0 / 6.0), (-5.0 / 6.0, 51.0)] {
+            assert!(ring
+                .iter()
+                .any(|c| (c.0 - corner.0).abs() < 1e-6 && (c.1 - corner.1).abs() < 1e-6));
+        }
+
+        // Square is clockwise in YAIXM, GeoJSON exterior is anti-clockwise
+        // (positive shoelace area)
+        let area: f64 = ring
+            .windows(2)
+            .map(|p| p[0].0 * p[1].1 - p[1].0 * p[0].1)
+            .sum();
+        assert!(area > 0.0);
+    }
+}
@@ -0,0 +1,210 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::yaixm::{Arc, Boundary, Circle, Feature, LatLon, Volume};
+use geo::{Bearing, Centroid, Coord, Destination, Geodesic, LineString, Polygon};
+
+// Default maximum distance (metres) between arc and its polygon approximation
+pub const DEFAULT_TOLERANCE: f64 = 50.0;
+
+// Angular step (degrees) so chord deviation from arc is within tolerance
+fn step_angle(radius: f64, tolerance: f64) -> f64 {
+    if radius <= tolerance {
+        90.0
+    } else {
+        (2.0 * (1.0 - tolerance / radius).acos().to_degrees()).clamp(0.1, 90.0)
+    }
+}
+
+fn coord(latlon: &LatLon) -> Coord<f64> {
+    latlon.point().into()
+}
+
+// Circle as closed ring of points
+fn circle_coords(circle: &Circle, tolerance: f64) -> Vec<Coord<f64>> {
+    let centre = circle.centre.point();
+    let radius = circle.radius.metres();
+    let n = (360.0 / step_angle(radius, tolerance)).ceil() as u32;
+
+    (0..=n)
+        .map(|i| {
+            let ang = f64::from(i % n) * 360.0 / f64::from(n);
+            Geodesic::destination(centre, ang, radius).into()
+        })
+        .collect()
+}
+
+// Points along an arc, excluding the start point, with arc within
+// tolerance (metres)
+pub fn arc_coords(arc: &Arc, from: &LatLon, tolerance: f64) -> Vec<Coord<f64>> {
+    arc_points(arc, from, step_angle(arc.radius.metres(), tolerance))
+}
+
+// Points along an arc, excluding the start point, at most step degrees
+// apart
+pub fn arc_points(arc: &Arc, from: &LatLon, step: f64) -> Vec<Coord<f64>> {
+    let centre = arc.centre.point();
+    let radius = arc.radius.metres();
+
+    let from_ang = Geodesic::bearing(centre, from.point());
+    let to_ang = Geodesic::bearing(centre, arc.to.point());
+
+    // Angle swept, positive clockwise
+    let sweep = if arc.dir == "cw" {
+        (to_ang - from_ang).rem_euclid(360.0)
+    } else {
+        -(from_ang - to_ang).rem_euclid(360.0)
+    };
+
+    let n = (sweep.abs() / step.clamp(0.1, 90.0)).ceil().max(1.0) as u32;
+
+    let mut coords = (1..n)
+        .map(|i| {
+            let ang = from_ang + sweep * f64::from(i) / f64::from(n);
+            Geodesic::destination(centre, ang, radius).into()
+        })
+        .collect::<Vec<Coord<f64>>>();
+    coords.push(coord(&arc.to));
+
+    coords
+}
+
+// Boundary as a polygon, arcs and circles approximated to within tolerance
+// (metres)
+pub fn boundary_polygon(boundary: &[Boundary], tolerance: f64) -> Polygon<f64> {
+    let mut coords: Vec<Coord<f64>> = Vec::new();
+    let mut prev: Option<&LatLon> = None;
+
+    for segment in boundary {
+        match segment {
+            Boundary::Line(line) => {
+                coords.extend(line.iter().map(coord));
+                prev = line.last();
+            }
+            Boundary::Arc(arc) => {
+                // Boundaries never start with an arc
+                if let Some(from) = prev {
+                    coords.extend(arc_coords(arc, from, tolerance));
+                }
+                prev = Some(&arc.to);
+            }
+            Boundary::Circle(circle) => coords.extend(circle_coords(circle, tolerance)),
+        }
+    }
+
+    // Polygon::new closes the exterior ring
+    Polygon::new(LineString::new(coords), vec![])
+}
+
+// Volume boundary as a polygon
+pub fn volume_polygon(volume: &Volume, tolerance: f64) -> Polygon<f64> {
+    boundary_polygon(&volume.boundary, tolerance)
+}
+
+// Centre of feature's first volume
+pub fn feature_centre(feature: &Feature) -> Option<LatLon> {
+    let volume = feature.geometry.first()?;
+    if let [Boundary::Circle(circle)] = volume.boundary.as_slice() {
+        Some(circle.centre)
+    } else {
+        volume_polygon(volume, DEFAULT_TOLERANCE)
+            .centroid()
+            .map(|c| LatLon::from_degrees(c.y(), c.x()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaixm::Distance;
+
+    const CENTRE: &str = "520000N 0010000W";
+
+    fn arc(dir: &str, to: &str) -> Arc {
+        Arc {
+            centre: CENTRE.parse().unwrap(),
+            dir: dir.to_string(),
+            radius: Distance::nm(5.0),
+            to: to.parse().unwrap(),
+        }
+    }
+
+    // Bearings (0 to 360) from arc centre to points
+    fn bearings(coords: &[Coord<f64>]) -> Vec<f64> {
+        let centre: LatLon = CENTRE.parse().unwrap();
+        coords
+            .iter()
+            .map(|c| Geodesic::bearing(centre.point(), (*c).into()).rem_euclid(360.0))
+            .collect()
+    }
+
+    // Points 5nm north, east and west of centre
+    const NORTH: &str = "520500N 0010000W";
+    const EAST: &str = "520000N 0005153W";
+    const WEST: &str = "520000N 0010807W";
+
+    #[test]
+    fn arc_clockwise() {
+        let coords = arc_points(&arc("cw", EAST), &NORTH.parse().unwrap(), 10.0);
+        let angles = bearings(&coords);
+
+        assert_eq!(coords.len(), 9);
+        assert!(angles.windows(2).all(|a| a[1] > a[0]));
+        assert!(angles[0] > 0.0 && angles[0] < 15.0);
+        assert_eq!(*coords.last().unwrap(), coord(&EAST.parse().unwrap()));
+    }
+
+    #[test]
+    fn arc_anticlockwise() {
+        // North to east, the long way round
+        let coords = arc_points(&arc("ccw", EAST), &NORTH.parse().unwrap(), 10.0);
+        let angles = bearings(&coords);
+
+        assert_eq!(coords.len(), 28);
+        assert!(angles.windows(2).all(|a| a[1] < a[0]));
+        assert!(angles[0] > 345.0);
+        assert_eq!(*coords.last().unwrap(), coord(&EAST.parse().unwrap()));
+    }
+
+    #[test]
+    fn arc_through_north() {
+        // West to east, clockwise through north
+        let coords = arc_points(&arc("cw", EAST), &WEST.parse().unwrap(), 10.0);
+        let angles = bearings(&coords);
+
+        assert_eq!(coords.len(), 18);
+        assert!(angles[..8].iter().all(|a| *a > 270.0));
+        assert!(angles[9..].iter().all(|a| *a < 90.5));
+    }
+
+    #[test]
+    fn arc_tolerance() {
+        let coarse = arc_coords(&arc("cw", EAST), &NORTH.parse().unwrap(), 500.0);
+        let fine = arc_coords(&arc("cw", EAST), &NORTH.parse().unwrap(), 10.0);
+        assert!(coarse.len() < fine.len());
+        assert_eq!(coarse.last(), fine.last());
+    }
+
+    #[test]
+    fn circle_closed() {
+        let circle = Circle {
+            centre: CENTRE.parse().unwrap(),
+            radius: Distance::nm(5.0),
+        };
+        let coords = circle_coords(&circle, DEFAULT_TOLERANCE);
+        assert_eq!(coords.first(), coords.last());
+        assert!(coords.len() > 4);
+    }
+}
@@ -0,0 +1,424 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::convert::{select_airspace, select_volumes};
+use crate::geometry::{volume_polygon, DEFAULT_TOLERANCE};
+use crate::settings::{AirType, Settings};
+use crate::yaixm::{Level, Yaixm};
+use geo::{BoundingRect, Closest, ClosestPoint, Contains, Distance, Geodesic, Intersects, Point};
+
+const FEET_PER_METRE: f64 = 3.28084;
+
+// Standard pressure setting (hPa)
+const STANDARD_PRESSURE: f64 = 1013.25;
+
+// Approximate change in pressure altitude per hPa near sea level
+const FEET_PER_HPA: f64 = 27.0;
+
+// Seconds in a day
+const DAY: u32 = 86400;
+
+// Altitude used for checking altitude (AMSL) limits. Flight levels are
+// always checked with pressure altitude
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AltitudeSource {
+    Gnss,
+    // Pressure altitude corrected with QNH (hPa)
+    Qnh(f64),
+}
+
+// IGC B record
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fix {
+    // Seconds since midnight UTC on the day the log starts
+    pub time: u32,
+    pub point: Point<f64>,
+    // Metres, pressure altitude is on the standard setting
+    pub pressure_alt: i32,
+    pub gnss_alt: i32,
+}
+
+impl Fix {
+    // Pressure altitude in feet
+    pub fn pressure_altitude(&self) -> f64 {
+        f64::from(self.pressure_alt) * FEET_PER_METRE
+    }
+
+    // Altitude above mean sea level in feet
+    pub fn altitude(&self, source: AltitudeSource) -> f64 {
+        match source {
+            AltitudeSource::Gnss => f64::from(self.gnss_alt) * FEET_PER_METRE,
+            AltitudeSource::Qnh(qnh) => {
+                self.pressure_altitude() + (qnh - STANDARD_PRESSURE) * FEET_PER_HPA
+            }
+        }
+    }
+}
+
+// Period inside an airspace volume
+#[derive(Clone, Debug, PartialEq)]
+pub struct Infringement {
+    pub name: String,
+    pub airtype: AirType,
+    pub lower: Level,
+    pub upper: Level,
+    // Seconds since midnight UTC on the day the log starts
+    pub start: u32,
+    pub end: u32,
+    // Maximum penetration, vertical (feet) and horizontal (metres)
+    pub vertical: f64,
+    pub horizontal: f64,
+}
+
+impl Infringement {
+    pub fn duration(&self) -> u32 {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+// Format seconds since midnight as HH:MM:SS
+pub fn format_time(secs: u32) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        (secs / 3600) % 24,
+        (secs / 60) % 60,
+        secs % 60
+    )
+}
+
+// Parse DDMMmmm[NS] or DDDMMmmm[EW], minutes to three decimal places
+fn parse_coord(value: &str, deg_len: usize) -> Option<f64> {
+    let deg: f64 = value.get(..deg_len)?.parse().ok()?;
+    let min: f64 = value.get(deg_len..deg_len + 5)?.parse().ok()?;
+    let deg = deg + min / 60000.0;
+
+    match value.get(deg_len + 5..)? {
+        "N" | "E" => Some(deg),
+        "S" | "W" => Some(-deg),
+        _ => None,
+    }
+}
+
+// Fix and validity, false for a 2D or no GNSS fix
+fn parse_b_record(line: &str) -> Option<(Fix, bool)> {
+    if !line.is_ascii() || line.len() < 35 {
+        return None;
+    }
+
+    let hh: u32 = line[1..3].parse().ok()?;
+    let mm: u32 = line[3..5].parse().ok()?;
+    let ss: u32 = line[5..7].parse().ok()?;
+    let lat = parse_coord(&line[7..15], 2)?;
+    let lon = parse_coord(&line[15..24], 3)?;
+    let valid = match &line[24..25] {
+        "A" => true,
+        "V" => false,
+        _ => return None,
+    };
+
+    let fix = Fix {
+        time: hh * 3600 + mm * 60 + ss,
+        point: Point::new(lon, lat),
+        pressure_alt: line[25..30].parse().ok()?,
+        gnss_alt: line[30..35].parse().ok()?,
+    };
+    Some((fix, valid))
+}
+
+// Parse IGC file B records, ignoring fixes without valid GNSS data
+pub fn parse_igc(text: &str) -> Result<Vec<Fix>, String> {
+    let mut fixes: Vec<Fix> = Vec::new();
+    let mut last_time = 0;
+    let mut day = 0;
+    for (n, line) in text.lines().enumerate() {
+        if line.starts_with('B') {
+            let (mut fix, valid) =
+                parse_b_record(line.trim_end()).ok_or(format!("invalid fix at line {}", n + 1))?;
+
+            // Times continue past midnight UTC
+            if fix.time < last_time {
+                day += DAY;
+            }
+            last_time = fix.time;
+            fix.time += day;
+
+            if valid {
+                fixes.push(fix);
+            }
+        }
+    }
+
+    if fixes.is_empty() {
+        Err("no fixes in IGC file".to_string())
+    } else {
+        Ok(fixes)
+    }
+}
+
+fn feet(level: Level) -> f64 {
+    f64::from(level.feet().unwrap_or(0))
+}
+
+// Fix altitude (feet) on the same datum as level: pressure altitude for
+// flight levels, otherwise altitude from source. Heights are compared
+// with altitude as terrain isn't known
+fn fix_altitude(fix: &Fix, level: Level, source: AltitudeSource, baro: bool) -> f64 {
+    match level {
+        Level::Fl(_) if baro => fix.pressure_altitude(),
+        _ => fix.altitude(source),
+    }
+}
+
+// Check flight against selected airspace
+pub fn check_flight(
+    yaixm: &Yaixm,
+    settings: &Settings,
+    fixes: &[Fix],
+    source: AltitudeSource,
+) -> Vec<Infringement> {
+    let airspace = select_airspace(yaixm, settings);
+
+    // Loggers without a pressure sensor record zero pressure altitude
+    let baro = fixes.iter().any(|fix| fix.pressure_alt != 0);
+    let source = if baro { source } else { AltitudeSource::Gnss };
+
+    let mut infringements = Vec::new();
+    for sel in select_volumes(&airspace, settings) {
+        let polygon = volume_polygon(sel.volume, DEFAULT_TOLERANCE);
+        let Some(rect) = polygon.bounding_rect() else {
+            continue;
+        };
+
+        let mut current: Option<Infringement> = None;
+        for fix in fixes {
+            // Distance above base and below top (feet)
+            let above = match sel.volume.lower {
+                Level::Sfc => f64::INFINITY,
+                level => fix_altitude(fix, level, source, baro) - feet(level),
+            };
+            let below = match sel.volume.upper {
+                Level::Unlimited => f64::INFINITY,
+                level => feet(level) - fix_altitude(fix, level, source, baro),
+            };
+
+            let inside = above >= 0.0
+                && below > 0.0
+                && rect.intersects(&fix.point)
+                && polygon.contains(&fix.point);
+
+            if inside {
+                let vertical = above.min(below);
+                let horizontal = match polygon.exterior().closest_point(&fix.point) {
+                    Closest::SinglePoint(p) | Closest::Intersection(p) => {
+                        Geodesic::distance(fix.point, p)
+                    }
+                    Closest::Indeterminate => 0.0,
+                };
+
+                let inf = current.get_or_insert_with(|| Infringement {
+                    name: sel.name(settings),
+                    airtype: sel.airtype,
+                    lower: sel.volume.lower,
+                    upper: sel.volume.upper,
+                    start: fix.time,
+                    end: fix.time,
+                    vertical: 0.0,
+                    horizontal: 0.0,
+                });
+                inf.end = fix.time;
+                inf.vertical = inf.vertical.max(vertical);
+                inf.horizontal = inf.horizontal.max(horizontal);
+            } else if let Some(inf) = current.take() {
+                infringements.push(inf);
+            }
+        }
+        infringements.extend(current);
+    }
+
+    infringements.sort_by_key(|inf| inf.start);
+    infringements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaixm::from_json;
+
+    // Test square, 51N-51:10N, 1W-0:50W from SFC to 2500 ft, with an
+    // extra FL45 to FL65 volume
+    fn square() -> Yaixm {
+        let mut json: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/data/square.json")).unwrap();
+        let mut volume = json["airspace"][0]["geometry"][0].clone();
+        volume["id"] = "square-2".into();
+        volume["lower"] = "FL45".into();
+        volume["upper"] = "FL65".into();
+        json["airspace"][0]["geometry"]
+            .as_array_mut()
+            .unwrap()
+            .push(volume);
+        from_json(&json.to_string()).unwrap()
+    }
+
+    // B record at the centre of the square, altitudes in metres
+    fn b_record(time: &str, valid: char, pressure: i32, gnss: i32) -> String {
+        format!(
+            "B{}5105000N00055000W{}{:05}{:05}",
+            time, valid, pressure, gnss
+        )
+    }
+
+    fn check(fixes: &[Fix], source: AltitudeSource) -> Vec<Infringement> {
+        check_flight(&square(), &Settings::default(), fixes, source)
+    }
+
+    #[test]
+    fn b_record_parse() {
+        let (fix, valid) = parse_b_record("B1101355206343N00006198WA0058700558").unwrap();
+        assert!(valid);
+        assert_eq!(fix.time, 11 * 3600 + 60 + 35);
+        assert!((fix.point.y() - (52.0 + 6.343 / 60.0)).abs() < 1e-9);
+        assert!((fix.point.x() + 6.198 / 60.0).abs() < 1e-9);
+        assert_eq!(fix.pressure_alt, 587);
+        assert_eq!(fix.gnss_alt, 558);
+
+        let (fix, valid) = parse_b_record("B1101355206343S00006198EV0058700558").unwrap();
+        assert!(!valid);
+        assert!(fix.point.y() < 0.0 && fix.point.x() > 0.0);
+    }
+
+    #[test]
+    fn b_record_invalid() {
+        for line in [
+            "B1101355206343N00006198WA00587",
+            "B1101355206343X00006198WA0058700558",
+            "B1101355206343N00006198WX0058700558",
+            "Bxx01355206343N00006198WA0058700558",
+        ] {
+            assert!(parse_b_record(line).is_none(), "{}", line);
+        }
+        assert!(parse_igc("B1101355206343N00006198WA00587\n").is_err());
+        assert!(parse_igc("HFDTE010125\n").is_err());
+    }
+
+    #[test]
+    fn igc_drops_invalid_fixes() {
+        let text = [
+            b_record("120000", 'A', 100, 100),
+            b_record("120001", 'V', 100, 0),
+            b_record("120002", 'A', 100, 100),
+        ]
+        .join("\n");
+        let fixes = parse_igc(&text).unwrap();
+        assert_eq!(fixes.len(), 2);
+        assert_eq!(fixes[1].time, 12 * 3600 + 2);
+    }
+
+    #[test]
+    fn igc_midnight() {
+        let text = [
+            b_record("235958", 'A', 500, 500),
+            b_record("235959", 'A', 500, 500),
+            b_record("000001", 'A', 500, 500),
+            b_record("000002", 'A', 1000, 1000),
+        ]
+        .join("\n");
+        let fixes = parse_igc(&text).unwrap();
+        assert_eq!(fixes[2].time, DAY + 1);
+
+        // Inside SFC to 2500 ft volume across midnight
+        let inf = check(&fixes[..3], AltitudeSource::Gnss);
+        assert_eq!(inf.len(), 1);
+        assert_eq!(inf[0].start, DAY - 2);
+        assert_eq!(inf[0].end, DAY + 1);
+        assert_eq!(inf[0].duration(), 3);
+        assert_eq!(format_time(inf[0].end), "00:00:01");
+    }
+
+    #[test]
+    fn flight_level_uses_pressure() {
+        // Pressure altitude 1500m (4921 ft) is above FL45, GNSS altitude
+        // 1300m (4265 ft) is below
+        let fixes = [Fix {
+            time: 0,
+            point: Point::new(-55.0 / 60.0, 51.0 + 5.0 / 60.0),
+            pressure_alt: 1500,
+            gnss_alt: 1300,
+        }];
+
+        for source in [AltitudeSource::Gnss, AltitudeSource::Qnh(1030.0)] {
+            let inf = check(&fixes, source);
+            assert_eq!(inf.len(), 1);
+            assert_eq!(inf[0].lower, Level::Fl(45));
+            assert!((inf[0].vertical - (1500.0 * FEET_PER_METRE - 4500.0)).abs() < 1.0);
+        }
+    }
+
+    #[test]
+    fn altitude_uses_source() {
+        // Pressure altitude 800m (2625 ft) is above 2500 ft, GNSS altitude
+        // 700m (2297 ft) is below
+        let fixes = [Fix {
+            time: 0,
+            point: Point::new(-55.0 / 60.0, 51.0 + 5.0 / 60.0),
+            pressure_alt: 800,
+            gnss_alt: 700,
+        }];
+
+        let inf = check(&fixes, AltitudeSource::Gnss);
+        assert_eq!(inf.len(), 1);
+        assert_eq!(inf[0].upper, Level::Altitude(2500));
+        assert!((inf[0].vertical - (2500.0 - 700.0 * FEET_PER_METRE)).abs() < 1.0);
+
+        // Standard setting, pressure altitude is above the volume
+        assert!(check(&fixes, AltitudeSource::Qnh(STANDARD_PRESSURE)).is_empty());
+
+        // Low QNH, altitude is 2625 - 20 * 27 = 2085 ft
+        let inf = check(&fixes, AltitudeSource::Qnh(993.25));
+        assert_eq!(inf.len(), 1);
+        assert!((inf[0].vertical - (2500.0 - 800.0 * FEET_PER_METRE + 540.0)).abs() < 1.0);
+    }
+
+    #[test]
+    fn no_pressure_sensor() {
+        // Zero pressure altitude, GNSS used for flight levels
+        let fixes = [Fix {
+            time: 0,
+            point: Point::new(-55.0 / 60.0, 51.0 + 5.0 / 60.0),
+            pressure_alt: 0,
+            gnss_alt: 1500,
+        }];
+        let inf = check(&fixes, AltitudeSource::Qnh(1013.0));
+        assert_eq!(inf.len(), 1);
+        assert_eq!(inf[0].lower, Level::Fl(45));
+    }
+
+    #[test]
+    fn outside() {
+        let mut fix = Fix {
+            time: 0,
+            point: Point::new(-1.5, 51.0 + 5.0 / 60.0),
+            pressure_alt: 300,
+            gnss_alt: 300,
+        };
+        assert!(check(&[fix.clone()], AltitudeSource::Gnss).is_empty());
+
+        // Between the volumes
+        fix.point = Point::new(-55.0 / 60.0, 51.0 + 5.0 / 60.0);
+        fix.pressure_alt = 1000;
+        fix.gnss_alt = 1000;
+        assert!(check(&[fix], AltitudeSource::Gnss).is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::convert::{select_airspace, select_volumes, SelectedVolume};
+use crate::geometry::{volume_polygon, DEFAULT_TOLERANCE};
+use crate::settings::{AirType, Pen, Settings};
+use crate::util::xml_escape;
+use crate::yaixm::Yaixm;
+use geo::{Coord, LineString};
+use std::fmt::Write;
+
+// Height for unlimited upper levels (feet)
+const UNLIMITED_FT: u32 = 66000;
+
+const FT_TO_M: f64 = 0.3048;
+
+// KML colour (aabbggrr) from pen colour
+fn kml_colour(pen: &Pen, alpha: &str) -> String {
+    let (r, g, b) = pen.rgb();
+    format!("{}{:02x}{:02x}{:02x}", alpha, b, g, r)
+}
+
+fn kml_coord(c: &Coord<f64>, alt: f64) -> String {
+    format!("{:.6},{:.6},{:.0}", c.x, c.y, alt)
+}
+
+fn kml_coords(ring: &LineString<f64>, alt: f64) -> String {
+    ring.coords()
+        .map(|c| kml_coord(c, alt))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn kml_polygon(out: &mut String, coords: &str, extrude: bool) {
+    let _ = writeln!(
+        out,
+        "<Polygon><extrude>{}</extrude><altitudeMode>absolute</altitudeMode>\
+         <outerBoundaryIs><LinearRing><coordinates>{}</coordinates></LinearRing></outerBoundaryIs>\
+         </Polygon>",
+        u8::from(extrude),
+        coords
+    );
+}
+
+// Volume as placemark. Surface based volumes are extruded to the ground,
+// others have floor, ceiling and wall polygons. Altitudes are approximate,
+// see Level::feet
+fn placemark(out: &mut String, sel: &SelectedVolume, settings: &Settings) {
+    let ring = volume_polygon(sel.volume, DEFAULT_TOLERANCE)
+        .exterior()
+        .clone();
+    let lower = f64::from(sel.volume.lower.feet().unwrap_or(0)) * FT_TO_M;
+    let upper = f64::from(sel.volume.upper.feet().unwrap_or(UNLIMITED_FT)) * FT_TO_M;
+
+    let mut desc = format!("{} - {}", sel.volume.lower, sel.volume.upper);
+    if let Some(freq) = sel.volume.frequency {
+        let _ = write!(desc, ", {:.3} MHz", freq);
+    }
+
+    let _ = writeln!(
+        out,
+        "<Placemark><name>{}</name><description>{}</description>\
+         <styleUrl>#{}</styleUrl><MultiGeometry>",
+        xml_escape(&sel.name(settings)),
+        xml_escape(&desc),
+        sel.airtype
+    );
+
+    kml_polygon(out, &kml_coords(&ring, upper), lower == 0.0);
+    if lower > 0.0 {
+        kml_polygon(out, &kml_coords(&ring, lower), false);
+        for edge in ring.lines() {
+            let coords = [
+                (edge.start, lower),
+                (edge.end, lower),
+                (edge.end, upper),
+                (edge.start, upper),
+                (edge.start, lower),
+            ]
+            .iter()
+            .map(|(c, alt)| kml_coord(c, *alt))
+            .collect::<Vec<String>>()
+            .join(" ");
+            kml_polygon(out, &coords, false);
+        }
+    }
+    out.push_str("</MultiGeometry></Placemark>\n");
+}
+
+// Generate KML document with 3D airspace volumes
+pub fn kml(yaixm: &Yaixm, settings: &Settings) -> String {
+    let airspace = select_airspace(yaixm, settings);
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>\n");
+    let _ = writeln!(
+        out,
+        "<name>UK Airspace {}</name>",
+        xml_escape(yaixm.release.date())
+    );
+
+    // One style for each type in use
+    let mut airtypes: Vec<AirType> = vec![];
+    for sel in select_volumes(&airspace, settings) {
+        if !airtypes.contains(&sel.airtype) {
+            airtypes.push(sel.airtype);
+        }
+    }
+    for airtype in airtypes {
+        let _ = writeln!(
+            out,
+            "<Style id=\"{}\"><LineStyle><color>{}</color><width>1.5</width></LineStyle>\
+             <PolyStyle><color>{}</color></PolyStyle></Style>",
+            airtype,
+            kml_colour(&settings.pen(airtype), "ff"),
+            kml_colour(&settings.pen(airtype), "60")
+        );
+    }
+
+    // Folder for each feature
+    for feature in &airspace {
+        let volumes = select_volumes(std::slice::from_ref(feature), settings).collect::<Vec<_>>();
+        if volumes.is_empty() {
+            continue;
+        }
+
+        let _ = writeln!(out, "<Folder><name>{}</name>", xml_escape(&feature.name));
+        for sel in volumes {
+            placemark(&mut out, &sel, settings);
+        }
+        out.push_str("</Folder>\n");
+    }
+
+    out.push_str("</Document>\n</kml>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaixm::from_json;
+
+    #[test]
+    fn square() {
+        let mut json: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/data/square.json")).unwrap();
+        let mut danger = json["airspace"][0].clone();
+        danger["id"] = "danger".into();
+        danger["name"] = "TEST DANGER".into();
+        danger["type"] = "D".into();
+        danger["class"] = serde_json::Value::Null;
+        danger["geometry"][0]["id"] = "danger-1".into();
+        danger["geometry"][0]["lower"] = "1000 ft".into();
+        json["airspace"].as_array_mut().unwrap().push(danger);
+        let yaixm = from_json(&json.to_string()).unwrap();
+
+        let settings = Settings::default();
+        let output = kml(&yaixm, &settings);
+
+        // One style per type, colour from type's pen
+        for airtype in [AirType::ClassD, AirType::Danger] {
+            let style = format!("<Style id=\"{}\">", airtype);
+            assert_eq!(output.matches(&style).count(), 1);
+            let url = format!("<styleUrl>#{}</styleUrl>", airtype);
+            assert_eq!(output.matches(&url).count(), 1);
+        }
+        assert!(output.contains(
+            "<Style id=\"ClassD\"><LineStyle><color>ffb4771f</color><width>1.5</width>\
+             </LineStyle><PolyStyle><color>60b4771f</color></PolyStyle></Style>"
+        ));
+
+        // Surface based square extruded from 2500 ft (762 m)
+        let square = output
+            .lines()
+            .find(|line| line.starts_with("<Placemark><name>SQUARE</name>"))
+            .unwrap();
+        let polygons = output
+            .split("<name>SQUARE</name>")
+            .nth(2)
+            .unwrap()
+            .split("</Placemark>")
+            .next()
+            .unwrap();
+        assert!(square.contains("<styleUrl>#ClassD</styleUrl>"));
+        assert_eq!(polygons.matches("<Polygon>").count(), 1);
+        assert!(polygons.contains("<extrude>1</extrude>"));
+        assert!(polygons.contains("-1.000000,51.000000,762"));
+
+        // Raised volume has ceiling, floor and four walls
+        let danger = output.split("<name>TEST DANGER</name>").nth(2).unwrap();
+        assert_eq!(danger.matches("<Polygon>").count(), 6);
+        assert!(!danger.contains("<extrude>1</extrude>"));
+        assert!(danger.contains("-1.000000,51.000000,305"));
+    }
+}
@@ -0,0 +1,33 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+pub mod aixm;
+pub mod convert;
+pub mod cub;
+pub mod geojson;
+pub mod geometry;
+pub mod igc;
+pub mod kml;
+pub mod lint;
+pub mod mvt;
+pub mod profile;
+pub mod query;
+pub mod route;
+pub mod settings;
+pub mod shapefile;
+pub mod tnp;
+pub mod util;
+pub mod waypoint;
+pub mod yaixm;
@@ -0,0 +1,341 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::convert::{openair_arc, openair_arc_ends, openair_line, openair_record, OpenAirRecord};
+use crate::geometry::{arc_coords, DEFAULT_TOLERANCE};
+use crate::yaixm::{Issue, LatLon, ARC_TOLERANCE};
+use geo::{Distance, Geodesic};
+
+// Airspace classes understood by common devices, UNCLASSIFIED is from
+// extended OpenAir where AY gives the type
+const CLASSES: [&str; 19] = [
+    "A",
+    "B",
+    "C",
+    "D",
+    "E",
+    "F",
+    "G",
+    "CTR",
+    "CTA",
+    "P",
+    "R",
+    "Q",
+    "W",
+    "GP",
+    "GSEC",
+    "MATZ",
+    "RMZ",
+    "TMZ",
+    "UNCLASSIFIED",
+];
+
+// Records which may appear after AC
+const RECORDS: [&str; 17] = [
+    "AN", "AL", "AH", "AF", "AG", "AI", "AY", "AA", "AT", "SP", "SB", "V", "DP", "DA", "DB", "DC",
+    "DY",
+];
+
+// Some devices fail with more points than this in one airspace, arcs
+// count as the points needed to draw them
+const MAX_POINTS: usize = 100;
+
+// Airspace being checked
+struct Airspace {
+    line: usize,
+    name: Option<String>,
+    lower: bool,
+    upper: bool,
+    centre: Option<LatLon>,
+    clockwise: bool,
+    first: Option<LatLon>,
+    last: Option<LatLon>,
+    points: usize,
+    circle: bool,
+}
+
+impl Airspace {
+    fn new(line: usize) -> Self {
+        Airspace {
+            line,
+            name: None,
+            lower: false,
+            upper: false,
+            centre: None,
+            clockwise: true,
+            first: None,
+            last: None,
+            points: 0,
+            circle: false,
+        }
+    }
+
+    fn name(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("AC at line {}", self.line))
+    }
+
+    fn point(&mut self, point: LatLon) {
+        self.first.get_or_insert(point);
+        self.last = Some(point);
+        self.points += 1;
+    }
+
+    fn arc(&mut self, centre: LatLon, from: LatLon, to: LatLon) {
+        let arc = openair_arc(centre, from, to, self.clockwise);
+        self.point(from);
+        self.last = Some(to);
+        self.points += arc_coords(&arc, &from, DEFAULT_TOLERANCE).len();
+    }
+
+    fn finish(&self, issues: &mut Vec<Issue>) {
+        let mut issue = |message: &str| {
+            issues.push(Issue::new(
+                &self.name(),
+                format!("line {}: {}", self.line, message),
+            ))
+        };
+
+        if self.name.is_none() {
+            issue("missing AN record");
+        }
+        if !self.lower {
+            issue("missing AL record");
+        }
+        if !self.upper {
+            issue("missing AH record");
+        }
+
+        if self.first.is_none() && !self.circle {
+            issue("no boundary");
+        } else if self.first.is_some() && self.circle {
+            issue("both circle and polygon boundary");
+        } else if self.first.is_some() && self.first != self.last {
+            issue("polygon is not closed, last point differs from first");
+        }
+
+        if self.points > MAX_POINTS {
+            issue(&format!(
+                "{} points, some devices are limited to {}",
+                self.points, MAX_POINTS
+            ));
+        }
+    }
+}
+
+// Check OpenAir data for problems likely to upset flight instruments.
+// Records are read as by the OpenAir parser
+pub fn lint_openair(text: &str) -> Vec<Issue> {
+    let mut issues = vec![];
+    let mut current: Option<Airspace> = None;
+
+    for (n, line) in text.lines().enumerate() {
+        let n = n + 1;
+        let Some((record, arg)) = openair_line(line) else {
+            continue;
+        };
+
+        if record == "AC" {
+            if let Some(airspace) = current.take() {
+                airspace.finish(&mut issues);
+            }
+            if !CLASSES.contains(&arg) {
+                issues.push(Issue::new(
+                    &format!("AC at line {}", n),
+                    format!("line {}: unknown class \"{}\"", n, arg),
+                ));
+            }
+            current = Some(Airspace::new(n));
+            continue;
+        }
+
+        let Some(airspace) = current.as_mut() else {
+            issues.push(Issue::new(
+                "Header",
+                format!("line {}: {} record before first AC", n, record),
+            ));
+            continue;
+        };
+
+        // Problems with this record
+        let mut messages = vec![];
+        match openair_record(record, arg) {
+            Err(err) => {
+                messages.push(err);
+
+                // Record is present, even if invalid
+                match record {
+                    "AN" => airspace.name = Some(arg.to_string()),
+                    "AL" => airspace.lower = true,
+                    "AH" => airspace.upper = true,
+                    "DC" => airspace.circle = true,
+                    _ => (),
+                }
+            }
+            Ok(OpenAirRecord::Name(name)) => {
+                airspace.name = Some(name.to_string());
+                if !name.is_ascii() {
+                    messages.push(format!("non-ASCII name \"{}\"", name));
+                }
+            }
+            Ok(OpenAirRecord::Lower(_)) => airspace.lower = true,
+            Ok(OpenAirRecord::Upper(_)) => airspace.upper = true,
+            Ok(OpenAirRecord::Centre(centre)) => airspace.centre = Some(centre),
+            Ok(OpenAirRecord::Clockwise(cw)) => airspace.clockwise = cw,
+            Ok(OpenAirRecord::Point(point)) => airspace.point(point),
+            Ok(OpenAirRecord::Circle(_)) => {
+                if airspace.centre.is_none() {
+                    messages.push("circle without V X= centre".to_string());
+                }
+                airspace.circle = true;
+            }
+            Ok(OpenAirRecord::ArcPoints(from, to)) => match airspace.centre {
+                Some(centre) => {
+                    let r1 = Geodesic::distance(centre.point(), from.point());
+                    let r2 = Geodesic::distance(centre.point(), to.point());
+                    if (r1 - r2).abs() > (r1 * ARC_TOLERANCE).max(100.0) {
+                        messages.push(format!(
+                            "arc end points are {:.0} m and {:.0} m from centre",
+                            r1, r2
+                        ));
+                    }
+                    airspace.arc(centre, from, to);
+                }
+                None => messages.push("arc without V X= centre".to_string()),
+            },
+            Ok(OpenAirRecord::ArcAngles(radius, start, end)) => match airspace.centre {
+                Some(centre) => {
+                    let (from, to) = openair_arc_ends(centre, radius, start, end);
+                    airspace.arc(centre, from, to);
+                }
+                None => messages.push("arc without V X= centre".to_string()),
+            },
+            Ok(_) => {
+                if !RECORDS.contains(&record) {
+                    messages.push(format!("unknown record \"{}\"", record));
+                }
+            }
+        }
+
+        for message in messages {
+            issues.push(Issue::new(
+                &airspace.name(),
+                format!("line {}: {}", n, message),
+            ));
+        }
+    }
+
+    if let Some(airspace) = current {
+        airspace.finish(&mut issues);
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: &str = "AC D\n\
+        AN SQUARE\n\
+        AL SFC\n\
+        AH 2500ft\n\
+        DP 51:00:00 N 001:00:00 W\n\
+        DP 51:10:00 N 001:00:00 W\n\
+        DP 51:10:00 N 000:50:00 W\n\
+        DP 51:00:00 N 000:50:00 W\n\
+        DP 51:00:00 N 001:00:00 W\n";
+
+    fn messages(text: &str) -> Vec<String> {
+        lint_openair(text)
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn clean() {
+        assert!(lint_openair(SQUARE).is_empty());
+
+        // Variables and arcs as written by the parser's tests
+        let arcs = "AC D\nAN ARCS\nAL SFC\nAH FL65\n\
+            V X = 52:00:00 N 001:00:00 W\n\
+            V D=-\n\
+            DB 52:05:00 N 001:00:00 W, 51:55:00 N 001:00:00 W\n\
+            V D=+\n\
+            DA 5,180,360\n";
+        assert_eq!(messages(arcs), Vec::<String>::new());
+    }
+
+    #[test]
+    fn missing_records() {
+        let text = SQUARE.replace("AN SQUARE\n", "").replace("AL SFC\n", "");
+        let messages = messages(&text);
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].ends_with("line 1: missing AN record"));
+        assert!(messages[1].ends_with("line 1: missing AL record"));
+    }
+
+    #[test]
+    fn bad_records() {
+        let text =
+            SQUARE.replace("AH 2500ft", "AH 2500 furlongs") + "AF 123,45\n" + "XX foo\n" + "AC Z\n";
+        let messages = messages(&text);
+        assert!(messages[0].ends_with("line 4: invalid level \"2500 furlongs\""));
+        assert!(messages[1].ends_with("line 10: invalid frequency \"123,45\""));
+        assert!(messages[2].ends_with("line 11: unknown record \"XX\""));
+        assert!(messages[3].ends_with("line 12: unknown class \"Z\""));
+    }
+
+    #[test]
+    fn not_closed() {
+        let text = SQUARE.trim_end().rsplit_once('\n').unwrap().0.to_string();
+        let messages = lint_openair(&text);
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].to_string().contains("polygon is not closed"));
+    }
+
+    #[test]
+    fn arc_centre() {
+        let text = "AC D\nAN ARC\nAL SFC\nAH FL65\n\
+            DB 52:05:00 N 001:00:00 W, 51:55:00 N 001:00:00 W\n";
+        assert!(messages(text)
+            .iter()
+            .any(|m| m.ends_with("line 5: arc without V X= centre")));
+
+        // End points at different distances from centre
+        let text = "AC D\nAN ARC\nAL SFC\nAH FL65\n\
+            V X=52:00:00 N 001:00:00 W\n\
+            DB 52:05:00 N 001:00:00 W, 51:50:00 N 001:00:00 W\n";
+        assert!(messages(text)
+            .iter()
+            .any(|m| m.contains("line 6: arc end points are")));
+    }
+
+    #[test]
+    fn arc_points() {
+        // Large circle drawn with arcs needs more than MAX_POINTS points
+        let text = "AC D\nAN BIG\nAL SFC\nAH FL65\n\
+            V X=52:00:00 N 001:00:00 W\n\
+            DA 100,0,180\n\
+            DA 100,180,360\n";
+        let messages = messages(text);
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("some devices are limited to 100"));
+
+        let small = text.replace("DA 100,", "DA 2,");
+        assert!(lint_openair(&small).is_empty());
+    }
+}
@@ -13,6 +13,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 //
+use chrono::Utc;
 use codee::string::JsonSerdeCodec;
 use futures::join;
 use gloo::file::{Blob, ObjectUrl};
@@ -23,18 +24,26 @@ use leptos::prelude::*;
 use leptos::web_sys;
 use leptos_use::storage::use_local_storage;
 
+use asselect::convert::openair;
+use asselect::cub::cub;
+use asselect::geojson::geojson;
+use asselect::kml::kml;
+use asselect::settings::{ExtraType, Format, Overlay, Settings};
+use asselect::shapefile::shapefile;
+use asselect::tnp::tnp;
+use asselect::waypoint::waypoints;
+use asselect::yaixm::{
+    self, gliding_sites, loa_names, rat_names, validate, wave_names, Cycle, DataFormat, Feature,
+    Manifest, Yaixm,
+};
 use components::{
-    about_tab::about_tab, airspace_tab::airspace_tab, extra_panel::extra_panel,
-    extra_tab::extra_tab, notam_tab::notam_tab, option_tab::option_tab, tabs::tabs,
+    about_tab::about_tab, airspace_tab::airspace_tab, data_file::data_file,
+    extra_panel::extra_panel, extra_tab::extra_tab, flight_tab::flight_tab,
+    issue_report::issue_report, notam_tab::notam_tab, option_tab::option_tab, query_tab::query_tab,
+    route_tab::route_tab, tabs::tabs,
 };
-use convert::openair;
-use settings::{ExtraType, Overlay, Settings};
-use yaixm::{gliding_sites, loa_names, rat_names, wave_names, Yaixm};
 
 mod components;
-mod convert;
-mod settings;
-mod yaixm;
 
 #[derive(Clone, Debug)]
 struct OverlayData {
@@ -44,7 +53,25 @@ struct OverlayData {
 }
 
 fn app() -> impl IntoView {
-    let async_yaixm = LocalResource::new(fetch_yaixm);
+    // Current or next AIRAC cycle data
+    let (cycle, set_cycle) = signal(Cycle::Current);
+    let async_manifest = LocalResource::new(fetch_manifest);
+
+    let async_yaixm = LocalResource::new(move || {
+        let cycle = cycle.get();
+        async move {
+            let today = Utc::now().format("%Y-%m-%d").to_string();
+            let path = async_manifest
+                .await
+                .and_then(|m| m.release(cycle, &today).map(|r| r.path.clone()))
+                .unwrap_or("yaixm.json".to_string());
+            fetch_yaixm(path).await
+        }
+    });
+
+    // Locally loaded YAIXM data, overrides server data
+    let (local_yaixm, set_local_yaixm) = signal(None::<Result<Yaixm, String>>);
+    provide_context(set_local_yaixm);
 
     let async_overlay = LocalResource::new(|| async {
         let overlay_195 = fetch_overlay("overlay_195.txt");
@@ -58,22 +85,46 @@ fn app() -> impl IntoView {
         }
     });
 
-    move || match async_yaixm.get().as_deref() {
-        Some(resource) => match resource {
-            Some(yaixm) => {
-                // This needs to use view! macro, otherwise reactive system breaks. Don't know why
-                view! {<MainView yaixm=yaixm.clone() overlay=async_overlay />}.into_any()
-            }
-            None => p().child("Error getting airspace data").into_any(),
-        },
-        None => p()
-            .child("Getting airspace data, please wait...")
-            .into_any(),
+    move || {
+        let local = local_yaixm.get();
+        let fetched = async_yaixm.get();
+
+        match local.as_ref().or(fetched.as_deref()) {
+            Some(resource) => match resource {
+                Ok(yaixm) => {
+                    // This needs to use view! macro, otherwise reactive system breaks. Don't know why
+                    view! {
+                        <MainView yaixm=yaixm.clone() overlay=async_overlay
+                            manifest=async_manifest cycle=cycle set_cycle=set_cycle />
+                    }
+                    .into_any()
+                }
+                Err(err) => div()
+                    .class("container block")
+                    .child((
+                        p().child("Error getting airspace data"),
+                        pre().child(err.clone()),
+                        p().class("mb-4")
+                            .child("Try reloading the page, or load a local file:"),
+                        data_file(),
+                    ))
+                    .into_any(),
+            },
+            None => p()
+                .child("Getting airspace data, please wait...")
+                .into_any(),
+        }
     }
 }
 
 #[component]
-fn MainView(yaixm: Yaixm, overlay: LocalResource<OverlayData>) -> impl IntoView {
+fn MainView(
+    yaixm: Yaixm,
+    overlay: LocalResource<OverlayData>,
+    manifest: LocalResource<Option<Manifest>>,
+    cycle: ReadSignal<Cycle>,
+    set_cycle: WriteSignal<Cycle>,
+) -> impl IntoView {
     // Local settings storage
     let (local_settings, set_local_settings, _) =
         use_local_storage::<Settings, JsonSerdeCodec>("settings");
@@ -83,6 +134,11 @@ fn MainView(yaixm: Yaixm, overlay: LocalResource<OverlayData>) -> impl IntoView
     provide_context(settings);
     provide_context(set_settings);
 
+    // Airspace from user OpenAir file, merged for download and tools
+    let (user_airspace, set_user_airspace) = signal(Vec::<Feature>::new());
+    provide_context(user_airspace);
+    provide_context(set_user_airspace);
+
     // Release note modal display control
     let (modal, set_modal) = signal(false);
 
@@ -102,21 +158,70 @@ fn MainView(yaixm: Yaixm, overlay: LocalResource<OverlayData>) -> impl IntoView
         }
     }
 
+    // Data problems
+    let issues = validate(&yaixm);
+
     let mut gliding_sites = gliding_sites(&yaixm);
     gliding_sites.sort();
 
-    let airac_date = yaixm.release.airac_date[..10].to_string();
+    let airac_date = yaixm.release.date().to_string();
     let release_note = yaixm.release.note.clone();
-    let filename = format!("uk{}.txt", airac_date);
+    let basename = format!("uk{}", airac_date);
 
     // UI static data
-    let tab_names = vec!["Main", "Option", "Extra", "NOTAM", "About"];
+    let tab_names = vec![
+        "Main", "Option", "Extra", "Query", "Route", "Flight", "NOTAM", "About",
+    ];
 
     let extra_names = vec!["Temporary Restrictions", "Local Agreements", "Wave Boxes"];
     let extra_ids = vec![ExtraType::Rat, ExtraType::Loa, ExtraType::Wave];
 
     let download_node_ref = NodeRef::<A>::new();
 
+    // Current/next release dates, if there is a next release
+    let set_local_yaixm = use_context::<WriteSignal<Option<Result<Yaixm, String>>>>()
+        .expect("to find local data setter");
+    let release_dates = move || {
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+        manifest.get().as_deref().flatten().and_then(|m| {
+            m.next(&today).map(|next| {
+                (
+                    m.current(&today)
+                        .map_or("Current".to_string(), |r| r.date().to_string()),
+                    next.date().to_string(),
+                )
+            })
+        })
+    };
+
+    // Switch cycle, keeping settings
+    let select_cycle = move |c: Cycle| {
+        set_local_settings.set(settings.get_untracked());
+        set_local_yaixm.set(None);
+        set_cycle.set(c);
+    };
+
+    // Waypoint download callback
+    let waypoint_yaixm = yaixm.clone();
+    let waypoint_name = format!("{}.cup", basename);
+    let download_waypoints = move |_| {
+        let data = waypoints(&waypoint_yaixm);
+        let blob = Blob::new(data.as_str());
+        let object_url = ObjectUrl::from(blob);
+
+        let a = download_node_ref.get().unwrap();
+        a.set_download(&waypoint_name);
+        a.set_href(&object_url);
+        a.click();
+    };
+
+    // Airspace with user airspace merged, for download and tools tabs
+    let merged_yaixm = Signal::derive(move || {
+        let mut yaixm = yaixm.clone();
+        yaixm.airspace.extend(user_airspace.get());
+        yaixm
+    });
+
     // Download button callback
     let download = move |_| {
         // Store settings
@@ -126,13 +231,7 @@ fn MainView(yaixm: Yaixm, overlay: LocalResource<OverlayData>) -> impl IntoView
             .and_then(|w| w.navigator().user_agent().ok())
             .unwrap_or_default();
 
-        // Create OpenAir data
-        let oa = if settings.get().overlay != Some(Overlay::AtzDzOnly) {
-            openair(&yaixm, &settings.get_untracked(), &user_agent)
-        } else {
-            // Overlay only, no airspace
-            "".to_string()
-        };
+        let yaixm = merged_yaixm.get_untracked();
 
         // Get overlay data
         let od = if let Some(overlay_setting) = settings.get().overlay {
@@ -150,23 +249,63 @@ fn MainView(yaixm: Yaixm, overlay: LocalResource<OverlayData>) -> impl IntoView
             "".to_string()
         };
 
+        let (data, fname) = match settings.get_untracked().format {
+            Format::GeoJson => (
+                geojson(&yaixm, &settings.get_untracked()).into_bytes(),
+                format!("{}.geojson", basename),
+            ),
+            Format::Kml => (
+                kml(&yaixm, &settings.get_untracked()).into_bytes(),
+                format!("{}.kml", basename),
+            ),
+            Format::Tnp => (
+                tnp(
+                    &yaixm,
+                    &settings.get_untracked(),
+                    Some(od.as_str()).filter(|od| !od.is_empty()),
+                    &user_agent,
+                )
+                .into_bytes(),
+                format!("{}.sua", basename),
+            ),
+            Format::Cub => (
+                cub(&yaixm, &settings.get_untracked()),
+                format!("{}.cub", basename),
+            ),
+            Format::Shapefile => (
+                shapefile(&yaixm, &settings.get_untracked(), &basename),
+                format!("{}.zip", basename),
+            ),
+            _ => {
+                // Create OpenAir data
+                let oa = if settings.get().overlay != Some(Overlay::AtzDzOnly) {
+                    openair(&yaixm, &settings.get_untracked(), &user_agent)
+                } else {
+                    // Overlay only, no airspace
+                    "".to_string()
+                };
+
+                let fname = if settings.get().overlay == Some(Overlay::AtzDzOnly) {
+                    "overlay.txt".to_string()
+                } else {
+                    format!("{}.txt", basename)
+                };
+                ((oa + od.as_str()).into_bytes(), fname)
+            }
+        };
+
         // Create download data
-        let blob = Blob::new((oa + od.as_str()).as_str());
+        let blob = Blob::new(data.as_slice());
         let object_url = ObjectUrl::from(blob);
-        let fname = if settings.get().overlay == Some(Overlay::AtzDzOnly) {
-            "overlay.txt"
-        } else {
-            &filename
-        };
 
         let a = download_node_ref.get().unwrap();
-        a.set_download(fname);
+        a.set_download(&fname);
         a.set_href(&object_url);
         a.click();
     };
 
     let children = vec![
-        airspace_tab(gliding_sites).into_any(),
+        airspace_tab(gliding_sites.clone()).into_any(),
         option_tab().into_any(),
         extra_tab(
             vec![
@@ -178,6 +317,9 @@ fn MainView(yaixm: Yaixm, overlay: LocalResource<OverlayData>) -> impl IntoView
             extra_ids,
         )
         .into_any(),
+        query_tab(merged_yaixm, gliding_sites).into_any(),
+        route_tab(merged_yaixm).into_any(),
+        flight_tab(merged_yaixm).into_any(),
         notam_tab().into_any(),
         about_tab().into_any(),
     ];
@@ -195,6 +337,14 @@ fn MainView(yaixm: Yaixm, overlay: LocalResource<OverlayData>) -> impl IntoView
                     ),
                 ),
             ),
+        // Validation report
+        div().class("container block").child(issue_report(
+            format!(
+                "Airspace data has {} problem(s), output may be incomplete:",
+                issues.len()
+            ),
+            issues,
+        )),
         // Tabs
         div()
             .class("container block")
@@ -207,10 +357,28 @@ fn MainView(yaixm: Yaixm, overlay: LocalResource<OverlayData>) -> impl IntoView
                     .class("button is-primary has-text-primary-100")
                     .on(ev::click, download)
                     .child("Get Airspace"),
+                button()
+                    .class("button is-info is-soft ml-2")
+                    .on(ev::click, download_waypoints)
+                    .child("Get Waypoints"),
                 a().id("airac-button")
                     .class("button is-text is-pulled-right")
                     .on(ev::click, move |_| set_modal.set(true))
                     .child(format!("AIRAC: {}", airac_date)),
+                move || {
+                    release_dates().map(|(current, next)| {
+                        div().class("buttons has-addons is-pulled-right").child((
+                            button()
+                                .class(move || cycle_class(cycle.get() == Cycle::Current))
+                                .on(ev::click, move |_| select_cycle(Cycle::Current))
+                                .child(format!("Current ({})", current)),
+                            button()
+                                .class(move || cycle_class(cycle.get() == Cycle::Next))
+                                .on(ev::click, move |_| select_cycle(Cycle::Next))
+                                .child(format!("Next ({})", next)),
+                        ))
+                    })
+                },
             )),
         ),
         // Release note overlay
@@ -229,6 +397,7 @@ fn MainView(yaixm: Yaixm, overlay: LocalResource<OverlayData>) -> impl IntoView
                     .child(div().class("box").child((
                         h2().class("subtitle").child("Release Details"),
                         pre().child(release_note),
+                        data_file(),
                     ))),
                 button()
                     .class("modal-close is-large")
@@ -239,15 +408,37 @@ fn MainView(yaixm: Yaixm, overlay: LocalResource<OverlayData>) -> impl IntoView
     )
 }
 
-// Get YAIXM data from server
-async fn fetch_yaixm() -> Option<Yaixm> {
-    let result = Request::get("yaixm.json").send().await;
+fn cycle_class(selected: bool) -> &'static str {
+    if selected {
+        "button is-info is-selected"
+    } else {
+        "button"
+    }
+}
+
+// Get list of available releases from server
+async fn fetch_manifest() -> Option<Manifest> {
+    let result = Request::get("releases/releases.json").send().await;
     match result {
-        Ok(response) => response.json().await.ok(),
+        Ok(response) if response.ok() => response.json().await.ok(),
         _ => None,
     }
 }
 
+// Get YAIXM data from server
+async fn fetch_yaixm(path: String) -> Result<Yaixm, String> {
+    let response = Request::get(&path)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.ok() {
+        return Err(format!("{}: {}", path, response.status_text()));
+    }
+    let format = DataFormat::detect(response.headers().get("content-type").as_deref(), &path);
+    let text = response.text().await.map_err(|e| e.to_string())?;
+    yaixm::parse(&text, format)
+}
+
 // Get overlay data from server
 async fn fetch_overlay(path: &str) -> Option<String> {
     let result = Request::get(path).send().await;
@@ -0,0 +1,444 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::convert::{select_airspace, select_volumes};
+use crate::geometry::{volume_polygon, DEFAULT_TOLERANCE};
+use crate::settings::Settings;
+use crate::yaixm::Yaixm;
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+// Mapbox vector tiles (MVT 2.1), single "airspace" layer
+
+const EXTENT: u32 = 4096;
+// Clip buffer outside tile, tile units
+const BUFFER: f64 = 64.0;
+
+const LAYER_NAME: &str = "airspace";
+
+// Deepest zoom, beyond this tiles are smaller than the data's accuracy
+pub const MAX_ZOOM: u8 = 20;
+
+// Geometry commands and type
+const CMD_MOVE_TO: u32 = 1;
+const CMD_LINE_TO: u32 = 2;
+const CMD_CLOSE_PATH: u32 = 7;
+const GEOM_POLYGON: u64 = 3;
+
+// Protobuf wire types
+const WIRE_VARINT: u32 = 0;
+const WIRE_64BIT: u32 = 1;
+const WIRE_LEN: u32 = 2;
+
+pub struct Tile {
+    pub z: u8,
+    pub x: u32,
+    pub y: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq)]
+enum Value {
+    String(String),
+    Double(f64),
+    Uint(u64),
+}
+
+// Feature attributes and outline in world coordinates (0..1)
+struct Shape {
+    attributes: Vec<(&'static str, Value)>,
+    ring: Vec<(f64, f64)>,
+    min: (f64, f64),
+    max: (f64, f64),
+}
+
+// Web Mercator world coordinates, origin top left
+fn project(lon: f64, lat: f64) -> (f64, f64) {
+    let lat = lat.clamp(-85.0511, 85.0511).to_radians();
+    let x = (lon + 180.0) / 360.0;
+    let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0;
+    (x, y)
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut x: u64) {
+    while x >= 0x80 {
+        buf.push((x as u8) | 0x80);
+        x >>= 7;
+    }
+    buf.push(x as u8);
+}
+
+fn put_key(buf: &mut Vec<u8>, field: u32, wire: u32) {
+    put_varint(buf, u64::from((field << 3) | wire));
+}
+
+fn put_bytes(buf: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    put_key(buf, field, WIRE_LEN);
+    put_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn put_uint(buf: &mut Vec<u8>, field: u32, x: u64) {
+    put_key(buf, field, WIRE_VARINT);
+    put_varint(buf, x);
+}
+
+fn put_packed(buf: &mut Vec<u8>, field: u32, values: &[u32]) {
+    let mut packed = vec![];
+    for v in values {
+        put_varint(&mut packed, u64::from(*v));
+    }
+    put_bytes(buf, field, &packed);
+}
+
+fn zigzag(x: i32) -> u32 {
+    ((x << 1) ^ (x >> 31)) as u32
+}
+
+fn encode_value(value: &Value) -> Vec<u8> {
+    let mut buf = vec![];
+    match value {
+        Value::String(s) => put_bytes(&mut buf, 1, s.as_bytes()),
+        Value::Double(d) => {
+            put_key(&mut buf, 3, WIRE_64BIT);
+            buf.extend_from_slice(&d.to_le_bytes());
+        }
+        Value::Uint(u) => put_uint(&mut buf, 5, *u),
+    }
+    buf
+}
+
+// Sutherland-Hodgman clip of ring to (convex) rectangle
+fn clip(ring: &[(f64, f64)], min: f64, max: f64) -> Vec<(f64, f64)> {
+    let edges: [(usize, f64, bool); 4] = [
+        (0, min, true),
+        (0, max, false),
+        (1, min, true),
+        (1, max, false),
+    ];
+
+    let mut output = ring.to_vec();
+    for (axis, limit, is_min) in edges {
+        let input = std::mem::take(&mut output);
+        let inside = |p: &(f64, f64)| {
+            let v = if axis == 0 { p.0 } else { p.1 };
+            if is_min {
+                v >= limit
+            } else {
+                v <= limit
+            }
+        };
+        let intersect = |a: &(f64, f64), b: &(f64, f64)| {
+            let (av, bv) = if axis == 0 { (a.0, b.0) } else { (a.1, b.1) };
+            let t = (limit - av) / (bv - av);
+            (a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1))
+        };
+
+        for (i, current) in input.iter().enumerate() {
+            let prev = &input[(i + input.len() - 1) % input.len()];
+            match (inside(prev), inside(current)) {
+                (true, true) => output.push(*current),
+                (true, false) => output.push(intersect(prev, current)),
+                (false, true) => {
+                    output.push(intersect(prev, current));
+                    output.push(*current);
+                }
+                (false, false) => (),
+            }
+        }
+    }
+    output
+}
+
+// Polygon geometry commands, exterior ring clockwise in tile coordinates
+fn encode_ring(ring: &[(f64, f64)]) -> Option<Vec<u32>> {
+    let mut points: Vec<(i32, i32)> = vec![];
+    for p in ring {
+        let q = (p.0.round() as i32, p.1.round() as i32);
+        if points.last() != Some(&q) {
+            points.push(q);
+        }
+    }
+    while points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    if points.len() < 3 {
+        return None;
+    }
+
+    // Surveyor's formula, positive for exterior ring
+    let area: i64 = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| i64::from(a.0) * i64::from(b.1) - i64::from(b.0) * i64::from(a.1))
+        .sum();
+    if area == 0 {
+        return None;
+    } else if area < 0 {
+        points.reverse();
+    }
+
+    let mut geometry = vec![CMD_MOVE_TO | (1 << 3)];
+    let mut cursor = (0, 0);
+    for (i, p) in points.iter().enumerate() {
+        if i == 1 {
+            geometry.push(CMD_LINE_TO | ((points.len() as u32 - 1) << 3));
+        }
+        geometry.push(zigzag(p.0 - cursor.0));
+        geometry.push(zigzag(p.1 - cursor.1));
+        cursor = *p;
+    }
+    geometry.push(CMD_CLOSE_PATH | (1 << 3));
+    Some(geometry)
+}
+
+// Encode layer of features with (tags, geometry)
+fn encode_tile(features: &[(&Shape, Vec<u32>)]) -> Vec<u8> {
+    let mut keys: Vec<&str> = vec![];
+    let mut values: Vec<Value> = vec![];
+
+    let mut layer = vec![];
+    put_uint(&mut layer, 15, 2);
+    put_bytes(&mut layer, 1, LAYER_NAME.as_bytes());
+
+    for (n, (shape, geometry)) in features.iter().enumerate() {
+        let mut tags = vec![];
+        for (key, value) in &shape.attributes {
+            let k = keys.iter().position(|k| k == key).unwrap_or_else(|| {
+                keys.push(key);
+                keys.len() - 1
+            });
+            let v = values.iter().position(|v| v == value).unwrap_or_else(|| {
+                values.push(value.clone());
+                values.len() - 1
+            });
+            tags.push(k as u32);
+            tags.push(v as u32);
+        }
+
+        let mut feature = vec![];
+        put_uint(&mut feature, 1, n as u64 + 1);
+        put_packed(&mut feature, 2, &tags);
+        put_uint(&mut feature, 3, GEOM_POLYGON);
+        put_packed(&mut feature, 4, geometry);
+        put_bytes(&mut layer, 2, &feature);
+    }
+
+    for key in keys {
+        put_bytes(&mut layer, 3, key.as_bytes());
+    }
+    for value in &values {
+        put_bytes(&mut layer, 4, &encode_value(value));
+    }
+    put_uint(&mut layer, 5, u64::from(EXTENT));
+
+    let mut tile = vec![];
+    put_bytes(&mut tile, 3, &layer);
+    tile
+}
+
+fn shapes(yaixm: &Yaixm, settings: &Settings) -> Vec<Shape> {
+    let airspace = select_airspace(yaixm, settings);
+
+    select_volumes(&airspace, settings)
+        .filter_map(|sel| {
+            let polygon = volume_polygon(sel.volume, DEFAULT_TOLERANCE);
+            let ring = polygon
+                .exterior()
+                .coords()
+                .map(|c| project(c.x, c.y))
+                .collect::<Vec<(f64, f64)>>();
+            if ring.is_empty() {
+                return None;
+            }
+
+            let min = ring
+                .iter()
+                .fold((f64::MAX, f64::MAX), |m, p| (m.0.min(p.0), m.1.min(p.1)));
+            let max = ring
+                .iter()
+                .fold((f64::MIN, f64::MIN), |m, p| (m.0.max(p.0), m.1.max(p.1)));
+
+            let mut attributes = vec![
+                ("name", Value::String(sel.name(settings))),
+                ("type", Value::String(sel.airtype.to_string())),
+                ("lower", Value::String(sel.volume.lower.to_string())),
+                ("upper", Value::String(sel.volume.upper.to_string())),
+            ];
+            if let Some(ft) = sel.volume.lower.feet() {
+                attributes.push(("lower_ft", Value::Uint(u64::from(ft))));
+            }
+            if let Some(ft) = sel.volume.upper.feet() {
+                attributes.push(("upper_ft", Value::Uint(u64::from(ft))));
+            }
+            if let Some(freq) = sel.volume.frequency {
+                attributes.push(("frequency", Value::Double(freq)));
+            }
+
+            Some(Shape {
+                attributes,
+                ring,
+                min,
+                max,
+            })
+        })
+        .collect()
+}
+
+// Vector tiles for zoom levels, omitting empty tiles
+pub fn tiles(
+    yaixm: &Yaixm,
+    settings: &Settings,
+    min_zoom: u8,
+    max_zoom: u8,
+) -> Result<Vec<Tile>, String> {
+    if min_zoom > max_zoom || max_zoom > MAX_ZOOM {
+        return Err(format!(
+            "Bad zoom range {}-{}, maximum zoom is {}",
+            min_zoom, max_zoom, MAX_ZOOM
+        ));
+    }
+
+    let shapes = shapes(yaixm, settings);
+
+    let mut tiles = vec![];
+    for z in min_zoom..=max_zoom {
+        let scale = f64::from(1u32 << z);
+        let max_tile = (1u32 << z) - 1;
+        let buffer = BUFFER / f64::from(EXTENT);
+
+        // Shapes for each tile, in tile order
+        let mut tile_shapes: BTreeMap<(u32, u32), Vec<usize>> = BTreeMap::new();
+        for (n, shape) in shapes.iter().enumerate() {
+            let tile_range = |lo: f64, hi: f64| {
+                let lo = ((lo * scale - buffer).floor().max(0.0) as u32).min(max_tile);
+                let hi = ((hi * scale + buffer).floor().max(0.0) as u32).min(max_tile);
+                lo..=hi
+            };
+            for x in tile_range(shape.min.0, shape.max.0) {
+                for y in tile_range(shape.min.1, shape.max.1) {
+                    tile_shapes.entry((x, y)).or_default().push(n);
+                }
+            }
+        }
+
+        for ((x, y), indices) in tile_shapes {
+            let features = indices
+                .iter()
+                .filter_map(|n| {
+                    let shape = &shapes[*n];
+                    let local = shape
+                        .ring
+                        .iter()
+                        .map(|p| {
+                            (
+                                (p.0 * scale - f64::from(x)) * f64::from(EXTENT),
+                                (p.1 * scale - f64::from(y)) * f64::from(EXTENT),
+                            )
+                        })
+                        .collect::<Vec<(f64, f64)>>();
+                    let clipped = clip(&local, -BUFFER, f64::from(EXTENT) + BUFFER);
+                    encode_ring(&clipped).map(|geometry| (shape, geometry))
+                })
+                .collect::<Vec<(&Shape, Vec<u32>)>>();
+
+            if !features.is_empty() {
+                tiles.push(Tile {
+                    z,
+                    x,
+                    y,
+                    data: encode_tile(&features),
+                });
+            }
+        }
+    }
+
+    Ok(tiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaixm::from_json;
+
+    fn varint(x: u64) -> Vec<u8> {
+        let mut buf = vec![];
+        put_varint(&mut buf, x);
+        buf
+    }
+
+    #[test]
+    fn varints() {
+        assert_eq!(varint(0), [0x00]);
+        assert_eq!(varint(1), [0x01]);
+        assert_eq!(varint(127), [0x7f]);
+        assert_eq!(varint(128), [0x80, 0x01]);
+        assert_eq!(varint(300), [0xac, 0x02]);
+        assert_eq!(varint(u64::MAX).len(), 10);
+
+        // Field 3, length delimited
+        let mut buf = vec![];
+        put_bytes(&mut buf, 3, b"ab");
+        assert_eq!(buf, [0x1a, 0x02, b'a', b'b']);
+    }
+
+    #[test]
+    fn zigzags() {
+        assert_eq!(zigzag(0), 0);
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+        assert_eq!(zigzag(-2), 3);
+        assert_eq!(zigzag(i32::MAX), 0xffff_fffe);
+        assert_eq!(zigzag(i32::MIN), 0xffff_ffff);
+    }
+
+    #[test]
+    fn ring_commands() {
+        // MoveTo(1), LineTo(3) and ClosePath(1) command integers
+        let square = [
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 10.0),
+            (0.0, 10.0),
+            (0.0, 0.0),
+        ];
+        let expected = vec![9, 0, 0, 26, 20, 0, 0, 20, 19, 0, 15];
+        assert_eq!(encode_ring(&square), Some(expected.clone()));
+
+        // Anticlockwise ring is reversed
+        let mut reversed = square;
+        reversed.reverse();
+        assert_eq!(encode_ring(&reversed[1..]), Some(expected));
+
+        // Degenerate rings
+        assert_eq!(encode_ring(&square[..2]), None);
+        assert_eq!(encode_ring(&[(0.0, 0.0), (5.0, 5.0), (10.0, 10.0)]), None);
+    }
+
+    #[test]
+    fn zoom_range() {
+        let yaixm = from_json(include_str!("../tests/data/square.json")).unwrap();
+        let settings = Settings::default();
+
+        let tiles = tiles(&yaixm, &settings, 0, 1).unwrap();
+        assert_eq!(
+            tiles.iter().map(|t| (t.z, t.x, t.y)).collect::<Vec<_>>(),
+            [(0, 0, 0), (1, 0, 0), (1, 1, 0)]
+        );
+
+        assert!(super::tiles(&yaixm, &settings, 0, 32).is_err());
+        assert!(super::tiles(&yaixm, &settings, 5, 4).is_err());
+    }
+}
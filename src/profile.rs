@@ -0,0 +1,214 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::route::{Crossing, Turnpoint};
+use crate::util::{airtype_colour, xml_escape};
+use geo::{Distance, Geodesic};
+use std::fmt::Write;
+
+// SVG size and plot margins
+const WIDTH: f64 = 800.0;
+const HEIGHT: f64 = 400.0;
+const MARGIN_LEFT: f64 = 60.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 20.0;
+const MARGIN_BOTTOM: f64 = 40.0;
+
+// Tick spacing giving around five ticks
+fn tick_step(range: f64) -> f64 {
+    let raw = range / 5.0;
+    let mag = 10f64.powf(raw.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|m| m * mag)
+        .find(|step| *step >= raw)
+        .unwrap_or(10.0 * mag)
+}
+
+// Tick values and labels from zero to range, labels with enough decimal
+// places for the tick step
+fn ticks(range: f64) -> Vec<(f64, String)> {
+    let step = tick_step(range);
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+
+    // Multiples of step, avoiding accumulated rounding errors
+    (0..)
+        .map(|n| f64::from(n) * step)
+        .take_while(|value| *value <= range + step * 1e-6)
+        .map(|value| (value, format!("{:.*}", decimals, value)))
+        .collect()
+}
+
+// Vertical cross-section of airspace along route as SVG. Altitudes are
+// approximate, see Level::feet, and plotted up to ceiling (feet)
+pub fn profile_svg(route: &[Turnpoint], crossings: &[Crossing], ceiling: u32) -> String {
+    // Distance to start of each leg
+    let mut offsets = vec![0.0];
+    for tps in route.windows(2) {
+        let len = Geodesic::distance(tps[0].position.point(), tps[1].position.point());
+        offsets.push(offsets.last().unwrap_or(&0.0) + len);
+    }
+    let total_km = (offsets.last().copied().unwrap_or(0.0) / 1000.0).max(1.0);
+    let ceiling = f64::from(ceiling.max(1000));
+
+    let plot_w = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+    let plot_h = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+    let x = |km: f64| MARGIN_LEFT + km / total_km * plot_w;
+    let y = |ft: f64| MARGIN_TOP + plot_h - ft.min(ceiling) / ceiling * plot_h;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\" \
+         font-family=\"sans-serif\" font-size=\"11\">\n",
+        WIDTH, HEIGHT
+    );
+
+    // Airspace slabs
+    for c in crossings {
+        let start = (offsets[c.leg] + c.entry) / 1000.0;
+        let end = (offsets[c.leg] + c.exit) / 1000.0;
+        let lower = f64::from(c.lower.feet().unwrap_or(0));
+        let upper = c.upper.feet().map_or(ceiling, f64::from);
+        if lower >= ceiling {
+            continue;
+        }
+
+        let colour = airtype_colour(c.airtype);
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" \
+             fill=\"{}\" fill-opacity=\"0.35\" stroke=\"{}\">\
+             <title>{} ({} - {})</title></rect>",
+            x(start),
+            y(upper),
+            (x(end) - x(start)).max(0.5),
+            y(lower) - y(upper),
+            colour,
+            colour,
+            xml_escape(&c.name),
+            c.lower,
+            c.upper
+        );
+    }
+
+    // Axes
+    let _ = writeln!(
+        svg,
+        "<path d=\"M{l:.1} {t:.1} V{b:.1} H{r:.1}\" fill=\"none\" stroke=\"black\"/>",
+        l = MARGIN_LEFT,
+        t = MARGIN_TOP,
+        b = MARGIN_TOP + plot_h,
+        r = MARGIN_LEFT + plot_w
+    );
+
+    for (ft, label) in ticks(ceiling) {
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+            MARGIN_LEFT - 4.0,
+            y(ft) + 4.0,
+            label
+        );
+    }
+
+    for (km, label) in ticks(total_km) {
+        let _ = writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+            x(km),
+            MARGIN_TOP + plot_h + 14.0,
+            label
+        );
+    }
+    let _ = writeln!(
+        svg,
+        "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">Distance (km)</text>\n\
+         <text x=\"12\" y=\"{:.1}\" text-anchor=\"middle\" \
+         transform=\"rotate(-90 12 {:.1})\">Altitude (ft)</text>",
+        MARGIN_LEFT + plot_w / 2.0,
+        HEIGHT - 6.0,
+        MARGIN_TOP + plot_h / 2.0,
+        MARGIN_TOP + plot_h / 2.0
+    );
+
+    // Turnpoints
+    for (tp, offset) in route.iter().zip(&offsets) {
+        let tx = x(offset / 1000.0);
+        let _ = writeln!(
+            svg,
+            "<line x1=\"{tx:.1}\" y1=\"{:.1}\" x2=\"{tx:.1}\" y2=\"{:.1}\" \
+             stroke=\"grey\" stroke-dasharray=\"4 4\"/>\
+             <text x=\"{tx:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+            MARGIN_TOP,
+            MARGIN_TOP + plot_h,
+            MARGIN_TOP - 6.0,
+            xml_escape(&tp.name)
+        );
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaixm::LatLon;
+
+    #[test]
+    fn step() {
+        assert_eq!(tick_step(10000.0), 2000.0);
+        assert_eq!(tick_step(5000.0), 1000.0);
+        assert_eq!(tick_step(120.0), 50.0);
+        assert_eq!(tick_step(3.0), 1.0);
+        assert!((tick_step(1.0) - 0.2).abs() < 1e-12);
+        assert!((tick_step(0.2) - 0.05).abs() < 1e-12);
+    }
+
+    #[test]
+    fn labels() {
+        let labels = |range| {
+            ticks(range)
+                .into_iter()
+                .map(|(_, label)| label)
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            labels(5000.0),
+            ["0", "1000", "2000", "3000", "4000", "5000"]
+        );
+        assert_eq!(labels(1.0), ["0.0", "0.2", "0.4", "0.6", "0.8", "1.0"]);
+        assert_eq!(labels(0.2), ["0.00", "0.05", "0.10", "0.15", "0.20"]);
+        assert_eq!(labels(13.0), ["0", "5", "10"]);
+    }
+
+    #[test]
+    fn svg_labels() {
+        // Leg of a little over 1 km, distance ticks every 0.5 km
+        let route = [
+            Turnpoint {
+                name: "A".to_string(),
+                position: LatLon::from_degrees(51.0, -1.0),
+            },
+            Turnpoint {
+                name: "B".to_string(),
+                position: LatLon::from_degrees(51.01, -1.0),
+            },
+        ];
+        let svg = profile_svg(&route, &[], 3000);
+        assert!(svg.contains(">0.5</text>"));
+        assert!(svg.contains(">3000</text>"));
+        assert!(!svg.contains("0000000"));
+    }
+}
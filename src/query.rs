@@ -0,0 +1,155 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::convert::{select_airspace, select_volumes};
+use crate::geometry::{feature_centre, volume_polygon, DEFAULT_TOLERANCE};
+use crate::settings::{AirType, Settings};
+use crate::yaixm::{IcaoType, LatLon, Level, LocalType, Yaixm};
+use geo::Contains;
+
+// Airspace volume above a position
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryResult {
+    pub name: String,
+    pub airtype: AirType,
+    pub lower: Level,
+    pub upper: Level,
+    pub frequency: Option<f64>,
+}
+
+// Parse position as YAIXM lat/lon or decimal degrees "lat, lon"
+pub fn parse_position(value: &str) -> Result<LatLon, String> {
+    let value = value.trim();
+    if let Ok(latlon) = value.parse::<LatLon>() {
+        return Ok(latlon);
+    }
+
+    let err = || format!("invalid position \"{}\"", value);
+    let mut parts = value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|x| !x.is_empty());
+    let (Some(lat), Some(lon), None) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(err());
+    };
+
+    let lat: f64 = lat.parse().map_err(|_| err())?;
+    let lon: f64 = lon.parse().map_err(|_| err())?;
+    if lat.abs() > 90.0 || lon.abs() > 180.0 {
+        return Err(err());
+    }
+
+    Ok(LatLon::from_degrees(lat, lon))
+}
+
+// Gliding site position
+pub fn site_position(yaixm: &Yaixm, name: &str) -> Option<LatLon> {
+    yaixm
+        .airspace
+        .iter()
+        .find(|f| {
+            f.icao_type == IcaoType::Other
+                && f.local_type == Some(LocalType::Glider)
+                && f.name == name
+        })
+        .and_then(feature_centre)
+}
+
+// All selected airspace volumes above position, lowest first
+pub fn airspace_at(yaixm: &Yaixm, settings: &Settings, position: &LatLon) -> Vec<QueryResult> {
+    let airspace = select_airspace(yaixm, settings);
+    let point = position.point();
+
+    let mut result = select_volumes(&airspace, settings)
+        .filter(|sel| volume_polygon(sel.volume, DEFAULT_TOLERANCE).contains(&point))
+        .map(|sel| QueryResult {
+            name: sel.name(settings),
+            airtype: sel.airtype,
+            lower: sel.volume.lower,
+            upper: sel.volume.upper,
+            frequency: sel.volume.frequency,
+        })
+        .collect::<Vec<QueryResult>>();
+
+    result.sort_by_key(|r| r.lower);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaixm::from_json;
+
+    fn square() -> serde_json::Value {
+        serde_json::from_str(include_str!("../tests/data/square.json")).unwrap()
+    }
+
+    #[test]
+    fn position() {
+        let latlon = LatLon::from_degrees(51.5, -1.25);
+        assert_eq!(parse_position("513000N 0011500W").unwrap(), latlon);
+        assert_eq!(parse_position(" 51.5, -1.25 ").unwrap(), latlon);
+        assert_eq!(parse_position("51.5 -1.25").unwrap(), latlon);
+        for value in [
+            "",
+            "51.5",
+            "51.5, -1.25, 0",
+            "north, west",
+            "91, 0",
+            "0, 181",
+        ] {
+            assert!(parse_position(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn site() {
+        let mut json = square();
+        let mut site = json["airspace"][0].clone();
+        site["name"] = "TEST GLIDING SITE".into();
+        site["type"] = "OTHER".into();
+        site["localtype"] = "GLIDER".into();
+        site["geometry"][0]["boundary"] =
+            serde_json::json!([{"circle": {"centre": "520000N 0010000W", "radius": "2 nm"}}]);
+        json["airspace"].as_array_mut().unwrap().push(site);
+        let yaixm = from_json(&json.to_string()).unwrap();
+
+        assert_eq!(
+            site_position(&yaixm, "TEST GLIDING SITE"),
+            Some(LatLon::from_degrees(52.0, -1.0))
+        );
+        // Not a gliding site
+        assert_eq!(site_position(&yaixm, "SQUARE"), None);
+        assert_eq!(site_position(&yaixm, "NOWHERE"), None);
+    }
+
+    #[test]
+    fn airspace() {
+        let yaixm = from_json(&square().to_string()).unwrap();
+        let settings = Settings::default();
+
+        let result = airspace_at(&yaixm, &settings, &LatLon::from_degrees(51.1, -0.9));
+        assert_eq!(
+            result,
+            [QueryResult {
+                name: "SQUARE".to_string(),
+                airtype: AirType::ClassD,
+                lower: Level::Sfc,
+                upper: Level::Altitude(2500),
+                frequency: Some(123.45),
+            }]
+        );
+        assert!(airspace_at(&yaixm, &settings, &LatLon::from_degrees(51.1, -0.5)).is_empty());
+    }
+}
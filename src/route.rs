@@ -0,0 +1,498 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::convert::{select_airspace, select_volumes};
+use crate::geometry::{volume_polygon, DEFAULT_TOLERANCE};
+use crate::query::{parse_position, site_position};
+use crate::settings::{AirType, Settings};
+use crate::yaixm::{LatLon, Level, Yaixm};
+use geo::line_intersection::{line_intersection, LineIntersection};
+use geo::{
+    Bearing, BoundingRect, Contains, Coord, Destination, Distance, Geodesic, Intersects, Line,
+    Point, Polygon, Rect,
+};
+
+// Maximum spacing (metres) of points used to follow a leg's geodesic
+const LEG_STEP: f64 = 2000.0;
+
+// Task turnpoint
+#[derive(Clone, Debug, PartialEq)]
+pub struct Turnpoint {
+    pub name: String,
+    pub position: LatLon,
+}
+
+// Airspace volume crossed by a task leg
+#[derive(Clone, Debug, PartialEq)]
+pub struct Crossing {
+    // Index of leg, first leg is zero
+    pub leg: usize,
+    pub name: String,
+    pub airtype: AirType,
+    pub lower: Level,
+    pub upper: Level,
+    pub frequency: Option<f64>,
+    // Entry and exit distances (metres) from start of leg
+    pub entry: f64,
+    pub exit: f64,
+}
+
+// Task leg, sampled along the geodesic
+pub struct Leg {
+    start: Point<f64>,
+    bearing: f64,
+    pub length: f64,
+    points: Vec<(f64, Coord<f64>)>,
+}
+
+impl Leg {
+    pub fn new(from: &LatLon, to: &LatLon) -> Self {
+        let start = from.point();
+        let end = to.point();
+        let bearing = Geodesic::bearing(start, end);
+        let length = Geodesic::distance(start, end);
+
+        let n = (length / LEG_STEP).ceil().max(1.0) as u32;
+        let points = (0..=n)
+            .map(|i| {
+                let dist = length * f64::from(i) / f64::from(n);
+                let point = if i == n {
+                    end
+                } else {
+                    Geodesic::destination(start, bearing, dist)
+                };
+                (dist, point.into())
+            })
+            .collect();
+
+        Leg {
+            start,
+            bearing,
+            length,
+            points,
+        }
+    }
+
+    // Position at distance (metres) along leg
+    pub fn position(&self, dist: f64) -> Point<f64> {
+        Geodesic::destination(self.start, self.bearing, dist)
+    }
+
+    fn bounding_rect(&self) -> Rect<f64> {
+        let (mut min, mut max) = (self.points[0].1, self.points[0].1);
+        for (_, c) in &self.points {
+            min = Coord {
+                x: min.x.min(c.x),
+                y: min.y.min(c.y),
+            };
+            max = Coord {
+                x: max.x.max(c.x),
+                y: max.y.max(c.y),
+            };
+        }
+        Rect::new(min, max)
+    }
+
+    // Distances along leg where it crosses polygon boundary
+    fn boundary_crossings(&self, polygon: &Polygon<f64>) -> Vec<f64> {
+        let mut dists = Vec::new();
+        for pair in self.points.windows(2) {
+            let ((d0, a), (d1, b)) = (pair[0], pair[1]);
+            let seg = Line::new(a, b);
+            let len2 = (b.x - a.x).powi(2) + (b.y - a.y).powi(2);
+
+            for edge in polygon.exterior().lines() {
+                if let Some(LineIntersection::SinglePoint { intersection, .. }) =
+                    line_intersection(seg, edge)
+                {
+                    let t = if len2 > 0.0 {
+                        ((intersection.x - a.x) * (b.x - a.x)
+                            + (intersection.y - a.y) * (b.y - a.y))
+                            / len2
+                    } else {
+                        0.0
+                    };
+                    dists.push(d0 + t.clamp(0.0, 1.0) * (d1 - d0));
+                }
+            }
+        }
+        dists
+    }
+
+    // Entry/exit distances of each part of the leg inside polygon
+    pub fn inside_intervals(&self, polygon: &Polygon<f64>) -> Vec<(f64, f64)> {
+        match polygon.bounding_rect() {
+            Some(rect) if rect.intersects(&self.bounding_rect()) => (),
+            _ => return vec![],
+        }
+
+        let mut dists = self.boundary_crossings(polygon);
+        dists.push(0.0);
+        dists.push(self.length);
+        dists.sort_by(f64::total_cmp);
+        dists.dedup_by(|a, b| (*a - *b).abs() < 1.0);
+
+        // Test the middle of each section between crossings
+        let mut intervals: Vec<(f64, f64)> = Vec::new();
+        for pair in dists.windows(2) {
+            let mid = self.position((pair[0] + pair[1]) / 2.0);
+            if polygon.contains(&mid) {
+                match intervals.last_mut() {
+                    Some(last) if (last.1 - pair[0]).abs() < 1.0 => last.1 = pair[1],
+                    _ => intervals.push((pair[0], pair[1])),
+                }
+            }
+        }
+        intervals
+    }
+}
+
+// All selected airspace crossed by route legs, in order along each leg
+pub fn route_crossings(yaixm: &Yaixm, settings: &Settings, route: &[Turnpoint]) -> Vec<Crossing> {
+    let airspace = select_airspace(yaixm, settings);
+    let volumes = select_volumes(&airspace, settings)
+        .map(|sel| (volume_polygon(sel.volume, DEFAULT_TOLERANCE), sel))
+        .collect::<Vec<_>>();
+
+    let mut crossings = Vec::new();
+    for (n, tps) in route.windows(2).enumerate() {
+        let leg = Leg::new(&tps[0].position, &tps[1].position);
+
+        let mut leg_crossings = Vec::new();
+        for (polygon, sel) in &volumes {
+            for (entry, exit) in leg.inside_intervals(polygon) {
+                leg_crossings.push(Crossing {
+                    leg: n,
+                    name: sel.name(settings),
+                    airtype: sel.airtype,
+                    lower: sel.volume.lower,
+                    upper: sel.volume.upper,
+                    frequency: sel.volume.frequency,
+                    entry,
+                    exit,
+                });
+            }
+        }
+        leg_crossings.sort_by(|a, b| a.entry.total_cmp(&b.entry));
+        crossings.append(&mut leg_crossings);
+    }
+
+    crossings
+}
+
+// Parse typed route, one turnpoint per line. Each line is a gliding site
+// name, a position, or a name and position separated by a colon
+pub fn parse_route(yaixm: &Yaixm, text: &str) -> Result<Vec<Turnpoint>, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            if let Some((name, position)) = line.split_once(':') {
+                Ok(Turnpoint {
+                    name: name.trim().to_string(),
+                    position: parse_position(position)?,
+                })
+            } else {
+                let position = match site_position(yaixm, line) {
+                    Some(position) => position,
+                    None => parse_position(line)?,
+                };
+                Ok(Turnpoint {
+                    name: line.to_string(),
+                    position,
+                })
+            }
+        })
+        .collect()
+}
+
+// Route as text, for editing
+pub fn format_route(route: &[Turnpoint]) -> String {
+    route
+        .iter()
+        .map(|tp| format!("{}: {}\n", tp.name, tp.position))
+        .collect()
+}
+
+// Split CSV line, handling quoted fields
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields.iter().map(|f| f.trim().to_string()).collect()
+}
+
+// Parse SeeYou DDMM.mmm[NS] or DDDMM.mmm[EW] coordinate
+fn parse_cup_coord(value: &str, deg_len: usize) -> Option<f64> {
+    let hemi = value.chars().last()?;
+    let digits = value.get(..value.len() - 1)?;
+
+    let deg: f64 = digits.get(..deg_len)?.parse().ok()?;
+    let min: f64 = digits.get(deg_len..)?.parse().ok()?;
+    let deg = deg + min / 60.0;
+
+    match hemi {
+        'N' | 'E' => Some(deg),
+        'S' | 'W' => Some(-deg),
+        _ => None,
+    }
+}
+
+// Parse first task in SeeYou .cup file
+pub fn parse_cup(text: &str) -> Result<Vec<Turnpoint>, String> {
+    let mut waypoints = Vec::new();
+    let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+
+    // Waypoints, skipping header line
+    for line in lines.by_ref() {
+        if line.starts_with("-----Related Tasks") {
+            break;
+        }
+
+        let fields = split_csv(line);
+        if fields.len() < 5 || fields[0].eq_ignore_ascii_case("name") {
+            continue;
+        }
+
+        let lat = parse_cup_coord(&fields[3], 2);
+        let lon = parse_cup_coord(&fields[4], 3);
+        match (lat, lon) {
+            (Some(lat), Some(lon)) => waypoints.push(Turnpoint {
+                name: fields[0].clone(),
+                position: LatLon::from_degrees(lat, lon),
+            }),
+            _ => return Err(format!("invalid waypoint \"{}\"", fields[0])),
+        }
+    }
+
+    // First task, task description is first field
+    let task = lines
+        .find(|l| !l.starts_with("Options") && !l.starts_with("ObsZone"))
+        .ok_or("no task found in file")?;
+
+    split_csv(task)
+        .iter()
+        .skip(1)
+        .filter(|name| !name.is_empty() && *name != "???")
+        .map(|name| {
+            waypoints
+                .iter()
+                .find(|wp| &wp.name == name)
+                .cloned()
+                .ok_or(format!("unknown task waypoint \"{}\"", name))
+        })
+        .collect()
+}
+
+// Parse XCSoar .tsk file
+pub fn parse_tsk(text: &str) -> Result<Vec<Turnpoint>, String> {
+    let doc = roxmltree::Document::parse(text).map_err(|e| e.to_string())?;
+
+    doc.descendants()
+        .filter(|n| n.has_tag_name("Point"))
+        .filter_map(|point| point.children().find(|n| n.has_tag_name("Waypoint")))
+        .map(|wp| {
+            let name = wp.attribute("name").unwrap_or_default().to_string();
+            let location = wp
+                .children()
+                .find(|n| n.has_tag_name("Location"))
+                .ok_or(format!("missing location for \"{}\"", name))?;
+
+            let coord = |attr| location.attribute(attr).and_then(|x| x.parse::<f64>().ok());
+            match (coord("latitude"), coord("longitude")) {
+                (Some(lat), Some(lon)) => Ok(Turnpoint {
+                    name,
+                    position: LatLon::from_degrees(lat, lon),
+                }),
+                _ => Err(format!("invalid location for \"{}\"", name)),
+            }
+        })
+        .collect()
+}
+
+// Parse task file, format from file name
+pub fn parse_task_file(name: &str, text: &str) -> Result<Vec<Turnpoint>, String> {
+    if name.to_ascii_lowercase().ends_with(".tsk") {
+        parse_tsk(text)
+    } else {
+        parse_cup(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaixm::from_json;
+
+    const CUP: &str = "\
+name,code,country,lat,lon,elev,style,rwdir,rwlen,freq,desc
+\"West, Start\",WST,GB,5105.000N,00106.000W,100.0m,1,,,,
+\"East \"\"Finish\"\"\",EFN,GB,5105.000N,00042.000W,100.0m,1,,,,
+-----Related Tasks-----
+\"Test task\",\"???\",\"West, Start\",\"East \"\"Finish\"\"\",\"???\"
+Options,NoStart=12:00:00
+ObsZone=0,Style=2,R1=500m
+";
+
+    const TSK: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Task type="RT">
+  <Point type="Start">
+    <Waypoint name="West" id="0" comment="" altitude="100">
+      <Location latitude="51.083333" longitude="-1.1"/>
+    </Waypoint>
+    <ObservationZone type="Line" length="1000"/>
+  </Point>
+  <Point type="Finish">
+    <Waypoint name="East" id="1" comment="" altitude="100">
+      <Location latitude="51.083333" longitude="-0.7"/>
+    </Waypoint>
+    <ObservationZone type="Cylinder" radius="500"/>
+  </Point>
+</Task>
+"#;
+
+    fn square() -> Yaixm {
+        from_json(include_str!("../tests/data/square.json")).unwrap()
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(split_csv("a, b ,c"), ["a", "b", "c"]);
+        assert_eq!(split_csv("\"a, b\",c"), ["a, b", "c"]);
+        assert_eq!(split_csv("\"say \"\"hi\"\"\",,"), ["say \"hi\"", "", ""]);
+    }
+
+    #[test]
+    fn cup_coord() {
+        let near = |x: Option<f64>, y: f64| (x.unwrap() - y).abs() < 1e-9;
+        assert!(near(parse_cup_coord("5105.000N", 2), 51.0 + 5.0 / 60.0));
+        assert!(near(parse_cup_coord("00106.000W", 3), -1.1));
+        assert!(near(parse_cup_coord("3330.500S", 2), -(33.0 + 30.5 / 60.0)));
+        assert_eq!(parse_cup_coord("5105.000X", 2), None);
+        assert_eq!(parse_cup_coord("51N", 2), None);
+        assert_eq!(parse_cup_coord("", 2), None);
+    }
+
+    #[test]
+    fn cup() {
+        let task = parse_cup(CUP).unwrap();
+        assert_eq!(task.len(), 2);
+        assert_eq!(task[0].name, "West, Start");
+        assert_eq!(
+            task[0].position,
+            LatLon::from_degrees(51.0 + 5.0 / 60.0, -1.1)
+        );
+        assert_eq!(task[1].name, "East \"Finish\"");
+        assert_eq!(
+            task[1].position,
+            LatLon::from_degrees(51.0 + 5.0 / 60.0, -0.7)
+        );
+
+        let no_task = CUP.split("-----").next().unwrap();
+        assert!(parse_cup(no_task).is_err());
+        assert!(parse_cup(&CUP.replace(",\"???\"\n", ",\"Nowhere\"\n")).is_err());
+        assert!(parse_cup(&CUP.replace("5105.000N", "5105.000X")).is_err());
+    }
+
+    #[test]
+    fn tsk() {
+        let task = parse_tsk(TSK).unwrap();
+        assert_eq!(
+            task,
+            [
+                Turnpoint {
+                    name: "West".to_string(),
+                    position: LatLon::from_degrees(51.083333, -1.1),
+                },
+                Turnpoint {
+                    name: "East".to_string(),
+                    position: LatLon::from_degrees(51.083333, -0.7),
+                },
+            ]
+        );
+        assert_eq!(parse_task_file("task.TSK", TSK).unwrap(), task);
+        assert!(parse_tsk(&TSK.replace("latitude=\"51.083333\"", "")).is_err());
+        assert!(parse_tsk("<Task").is_err());
+    }
+
+    #[test]
+    fn route() {
+        let route = parse_route(&square(), "Start: 51.083333, -1.1\n\n510500N 0004200W\n").unwrap();
+        assert_eq!(route[0].name, "Start");
+        assert_eq!(route[0].position, LatLon::from_degrees(51.083333, -1.1));
+        assert_eq!(route[1].name, "510500N 0004200W");
+        assert_eq!(
+            parse_route(&square(), &format_route(&route)).unwrap()[1].position,
+            route[1].position
+        );
+
+        assert!(parse_route(&square(), "Nowhere").is_err());
+        assert!(parse_route(&square(), "Start: 91, 0").is_err());
+    }
+
+    // Leg at 51:05N from 1:06W to 0:42W enters the square at 1W and
+    // leaves at 0:50W
+    #[test]
+    fn crossings() {
+        let task = parse_tsk(TSK).unwrap();
+        let dist = |lon: f64| {
+            Geodesic::distance(
+                task[0].position.point(),
+                LatLon::from_degrees(51.083333, lon).point(),
+            )
+        };
+
+        let leg = Leg::new(&task[0].position, &task[1].position);
+        let polygon = volume_polygon(&square().airspace[0].geometry[0], DEFAULT_TOLERANCE);
+        let intervals = leg.inside_intervals(&polygon);
+        assert_eq!(intervals.len(), 1);
+        assert!((intervals[0].0 - dist(-1.0)).abs() < 50.0);
+        assert!((intervals[0].1 - dist(-50.0 / 60.0)).abs() < 50.0);
+
+        // Leg clear of the square
+        let clear = Leg::new(
+            &LatLon::from_degrees(52.0, -1.1),
+            &LatLon::from_degrees(52.0, -0.7),
+        );
+        assert!(clear.inside_intervals(&polygon).is_empty());
+
+        let crossings = route_crossings(&square(), &Settings::default(), &task);
+        assert_eq!(crossings.len(), 1);
+        assert_eq!(crossings[0].leg, 0);
+        assert_eq!(crossings[0].name, "SQUARE");
+        assert_eq!(crossings[0].airtype, AirType::ClassD);
+        assert_eq!(
+            (crossings[0].lower, crossings[0].upper),
+            (Level::Sfc, Level::Altitude(2500))
+        );
+        assert_eq!(crossings[0].frequency, Some(123.45));
+        assert_eq!((crossings[0].entry, crossings[0].exit), intervals[0]);
+    }
+}
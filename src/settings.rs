@@ -13,13 +13,28 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 //
+use crate::util::airtype_colour;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
 use std::str::FromStr;
-use strum_macros::{AsRefStr, Display, EnumString};
+use strum_macros::{AsRefStr, Display, EnumIter, EnumString};
 
 // Airspace types
-#[derive(AsRefStr, Clone, Copy, Debug, Deserialize, Display, EnumString, PartialEq, Serialize)]
+#[derive(
+    AsRefStr,
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Display,
+    EnumIter,
+    EnumString,
+    Eq,
+    Hash,
+    PartialEq,
+    Serialize,
+)]
 pub enum AirType {
     ClassA,
     ClassB,
@@ -40,10 +55,73 @@ pub enum AirType {
     Tmz,
 }
 
+// OpenAir pen, colour is #rrggbb
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Pen {
+    pub colour: String,
+    pub width: u8,
+}
+
+impl Pen {
+    // Colour must be #rrggbb, anything else is replaced by the default
+    pub fn is_valid(&self) -> bool {
+        self.colour.len() == 7
+            && self.colour.starts_with('#')
+            && self.colour[1..].chars().all(|c| c.is_ascii_hexdigit())
+    }
+
+    pub fn rgb(&self) -> (u8, u8, u8) {
+        let hex = self.colour.trim_start_matches('#');
+        let component = |n: usize| {
+            hex.get(n..n + 2)
+                .and_then(|x| u8::from_str_radix(x, 16).ok())
+                .unwrap_or(0)
+        };
+        (component(0), component(2), component(4))
+    }
+}
+
+impl AirType {
+    // Default pen
+    pub fn pen(&self) -> Pen {
+        let width = match self {
+            AirType::ClassA
+            | AirType::ClassB
+            | AirType::ClassC
+            | AirType::ClassD
+            | AirType::Cta
+            | AirType::Ctr
+            | AirType::Danger
+            | AirType::Prohibited
+            | AirType::Restricted => 2,
+            _ => 1,
+        };
+        Pen {
+            colour: airtype_colour(*self).to_string(),
+            width,
+        }
+    }
+}
+
 // Output format
 #[derive(AsRefStr, Clone, Debug, Deserialize, Display, EnumString, PartialEq, Serialize)]
 pub enum Format {
     OpenAir,
+    OpenAirExtended,
+    GeoJson,
+    Kml,
+    Tnp,
+    Cub,
+    Shapefile,
+}
+
+// Airspace selection mode, for any output format
+#[derive(
+    AsRefStr, Clone, Debug, Default, Deserialize, Display, EnumString, PartialEq, Serialize,
+)]
+pub enum Mode {
+    #[default]
+    Normal,
     RatOnly,
     Competition,
 }
@@ -58,7 +136,8 @@ pub enum Overlay {
 }
 
 // Settings
-#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Deserialize, PartialEq, Serialize)]
+#[serde(from = "SavedSettings")]
 pub struct Settings {
     pub atz: AirType,
     pub ils: Option<AirType>,
@@ -70,7 +149,12 @@ pub struct Settings {
     pub obstacle: Option<AirType>,
     pub max_level: u16,
     pub radio: bool,
+    // OpenAir SP/SB pen and brush records
+    #[serde(default)]
+    pub styles: bool,
     pub format: Format,
+    #[serde(default)]
+    pub mode: Mode,
     pub overlay: Option<Overlay>,
     #[serde(default)]
     pub loa: HashSet<String>,
@@ -78,6 +162,9 @@ pub struct Settings {
     pub rat: HashSet<String>,
     #[serde(default)]
     pub wave: HashSet<String>,
+    // User chosen pens, otherwise default for type
+    #[serde(default)]
+    pub pens: HashMap<AirType, Pen>,
 }
 
 impl Default for Settings {
@@ -93,11 +180,127 @@ impl Default for Settings {
             obstacle: None,
             max_level: 660,
             radio: false,
+            styles: false,
             format: Format::OpenAir,
+            mode: Mode::Normal,
             overlay: None,
             loa: HashSet::new(),
             rat: HashSet::new(),
             wave: HashSet::new(),
+            pens: HashMap::new(),
+        }
+    }
+}
+
+// Sets and pens are sorted, so output file headers are repeatable
+impl fmt::Debug for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sorted = |set: &HashSet<String>| set.iter().cloned().collect::<BTreeSet<String>>();
+        let pens = self
+            .pens
+            .iter()
+            .map(|(airtype, pen)| (airtype.as_ref(), pen))
+            .collect::<BTreeMap<&str, &Pen>>();
+
+        f.debug_struct("Settings")
+            .field("atz", &self.atz)
+            .field("ils", &self.ils)
+            .field("unlicensed", &self.unlicensed)
+            .field("microlight", &self.microlight)
+            .field("gliding", &self.gliding)
+            .field("home", &self.home)
+            .field("hirta_gvs", &self.hirta_gvs)
+            .field("obstacle", &self.obstacle)
+            .field("max_level", &self.max_level)
+            .field("radio", &self.radio)
+            .field("styles", &self.styles)
+            .field("format", &self.format)
+            .field("mode", &self.mode)
+            .field("overlay", &self.overlay)
+            .field("loa", &sorted(&self.loa))
+            .field("rat", &sorted(&self.rat))
+            .field("wave", &sorted(&self.wave))
+            .field("pens", &pens)
+            .finish()
+    }
+}
+
+// Format in saved settings, selection modes were formats in older
+// versions
+#[derive(Deserialize)]
+enum SavedFormat {
+    OpenAir,
+    OpenAirExtended,
+    GeoJson,
+    Kml,
+    Tnp,
+    Cub,
+    Shapefile,
+    RatOnly,
+    Competition,
+}
+
+// Settings as saved, converted to current settings
+#[derive(Deserialize)]
+struct SavedSettings {
+    atz: AirType,
+    ils: Option<AirType>,
+    unlicensed: Option<AirType>,
+    microlight: Option<AirType>,
+    gliding: Option<AirType>,
+    home: Option<String>,
+    hirta_gvs: Option<AirType>,
+    obstacle: Option<AirType>,
+    max_level: u16,
+    radio: bool,
+    #[serde(default)]
+    styles: bool,
+    format: SavedFormat,
+    mode: Option<Mode>,
+    overlay: Option<Overlay>,
+    #[serde(default)]
+    loa: HashSet<String>,
+    #[serde(default)]
+    rat: HashSet<String>,
+    #[serde(default)]
+    wave: HashSet<String>,
+    #[serde(default)]
+    pens: HashMap<AirType, Pen>,
+}
+
+impl From<SavedSettings> for Settings {
+    fn from(saved: SavedSettings) -> Self {
+        let (format, legacy_mode) = match saved.format {
+            SavedFormat::OpenAir => (Format::OpenAir, None),
+            SavedFormat::OpenAirExtended => (Format::OpenAirExtended, None),
+            SavedFormat::GeoJson => (Format::GeoJson, None),
+            SavedFormat::Kml => (Format::Kml, None),
+            SavedFormat::Tnp => (Format::Tnp, None),
+            SavedFormat::Cub => (Format::Cub, None),
+            SavedFormat::Shapefile => (Format::Shapefile, None),
+            SavedFormat::RatOnly => (Format::OpenAir, Some(Mode::RatOnly)),
+            SavedFormat::Competition => (Format::OpenAir, Some(Mode::Competition)),
+        };
+
+        Settings {
+            atz: saved.atz,
+            ils: saved.ils,
+            unlicensed: saved.unlicensed,
+            microlight: saved.microlight,
+            gliding: saved.gliding,
+            home: saved.home,
+            hirta_gvs: saved.hirta_gvs,
+            obstacle: saved.obstacle,
+            max_level: saved.max_level,
+            radio: saved.radio,
+            styles: saved.styles,
+            format,
+            mode: legacy_mode.or(saved.mode).unwrap_or_default(),
+            overlay: saved.overlay,
+            loa: saved.loa,
+            rat: saved.rat,
+            wave: saved.wave,
+            pens: saved.pens,
         }
     }
 }
@@ -120,8 +323,10 @@ impl Settings {
             "hirta_gvs" => self.hirta_gvs = AirType::from_str(value).ok(),
             "obstacle" => self.obstacle = AirType::from_str(value).ok(),
             "format" => self.format = Format::from_str(value).unwrap_or(Format::OpenAir),
+            "mode" => self.mode = Mode::from_str(value).unwrap_or_default(),
             "max_level" => self.max_level = value.parse().unwrap_or(660),
             "radio" => self.radio = value == "yes",
+            "styles" => self.styles = value == "yes",
             "overlay" => self.overlay = Overlay::from_str(value).ok(),
             "home" => {
                 self.home = if value == "no" {
@@ -134,6 +339,27 @@ impl Settings {
         }
     }
 
+    pub fn pen(&self, airtype: AirType) -> Pen {
+        self.pens
+            .get(&airtype)
+            .filter(|pen| pen.is_valid())
+            .cloned()
+            .unwrap_or_else(|| airtype.pen())
+    }
+
+    pub fn set_colour(&mut self, airtype: AirType, colour: &str) {
+        let mut pen = self.pen(airtype);
+        pen.colour = colour.to_string();
+        if !pen.is_valid() {
+            return;
+        }
+        if pen == airtype.pen() {
+            self.pens.remove(&airtype);
+        } else {
+            self.pens.insert(airtype, pen);
+        }
+    }
+
     pub fn set_extra(&mut self, id: ExtraType, value: &str, add: bool) {
         let x = match id {
             ExtraType::Rat => &mut self.rat,
@@ -164,3 +390,78 @@ impl Settings {
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_format() {
+        // Settings saved when selection modes were formats
+        let json = serde_json::to_string(&Settings::default())
+            .unwrap()
+            .replace("\"format\":\"OpenAir\"", "\"format\":\"Competition\"")
+            .replace(",\"mode\":\"Normal\"", "");
+        let settings: Settings = serde_json::from_str(&json).unwrap();
+        assert_eq!(settings.format, Format::OpenAir);
+        assert_eq!(settings.mode, Mode::Competition);
+
+        let json = json.replace("\"Competition\"", "\"RatOnly\"");
+        let settings: Settings = serde_json::from_str(&json).unwrap();
+        assert_eq!(settings.format, Format::OpenAir);
+        assert_eq!(settings.mode, Mode::RatOnly);
+    }
+
+    #[test]
+    fn saved_round_trip() {
+        let mut settings = Settings::default();
+        settings.update("format", "Kml");
+        settings.update("mode", "Competition");
+        settings.rat.insert("TEST RAT".to_string());
+        settings.set_colour(AirType::Danger, "#102030");
+
+        let json = serde_json::to_string(&settings).unwrap();
+        assert_eq!(serde_json::from_str::<Settings>(&json).unwrap(), settings);
+    }
+
+    #[test]
+    fn mode_with_format() {
+        let mut settings = Settings::default();
+        settings.update("format", "Tnp");
+        settings.update("mode", "RatOnly");
+        assert_eq!(settings.format, Format::Tnp);
+        assert_eq!(settings.mode, Mode::RatOnly);
+
+        settings.update("mode", "bad");
+        assert_eq!(settings.mode, Mode::Normal);
+    }
+
+    #[test]
+    fn invalid_colour() {
+        let mut settings = Settings::default();
+        settings.set_colour(AirType::Danger, "#12345");
+        settings.set_colour(AirType::Danger, "red");
+        assert!(settings.pens.is_empty());
+
+        settings.set_colour(AirType::Danger, "#00ff80");
+        assert_eq!(settings.pen(AirType::Danger).rgb(), (0, 255, 128));
+
+        // Bad colour from saved settings
+        settings.pens.get_mut(&AirType::Danger).unwrap().colour = "#zz".to_string();
+        assert_eq!(settings.pen(AirType::Danger), AirType::Danger.pen());
+    }
+
+    #[test]
+    fn debug_sorted() {
+        let mut settings = Settings::default();
+        for name in ["b", "c", "a"] {
+            settings.rat.insert(name.to_string());
+        }
+        settings.set_colour(AirType::Tmz, "#000001");
+        settings.set_colour(AirType::ClassA, "#000002");
+
+        let text = format!("{:?}", settings);
+        assert!(text.contains("rat: {\"a\", \"b\", \"c\"}"));
+        assert!(text.find("ClassA").unwrap() < text.find("Tmz").unwrap());
+    }
+}
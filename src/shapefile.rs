@@ -0,0 +1,377 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::convert::{select_airspace, select_volumes};
+use crate::geometry::{volume_polygon, DEFAULT_TOLERANCE};
+use crate::settings::Settings;
+use crate::yaixm::Yaixm;
+use geo::orient::{Direction, Orient};
+use geo::{BoundingRect, Polygon};
+
+// ESRI Shapefile (polygons, WGS84) with dBase attributes, as a zip file
+
+const SHAPE_POLYGON: i32 = 5;
+
+const PRJ: &str = "GEOGCS[\"GCS_WGS_1984\",DATUM[\"D_WGS_1984\",\
+    SPHEROID[\"WGS_1984\",6378137.0,298.257223563]],\
+    PRIMEM[\"Greenwich\",0.0],UNIT[\"Degree\",0.0174532925199433]]";
+
+// dBase field name, type, length and decimal places
+const FIELDS: [(&str, u8, usize, u8); 7] = [
+    ("NAME", b'C', 100, 0),
+    ("TYPE", b'C', 16, 0),
+    ("CLASS", b'C', 1, 0),
+    ("LOWER_FT", b'N', 6, 0),
+    ("UPPER_FT", b'N', 6, 0),
+    ("FREQ", b'N', 7, 3),
+    ("RULES", b'C', 40, 0),
+];
+
+// Text truncated to field length, on a character boundary
+fn field_text(text: &str, len: usize) -> &str {
+    let mut end = text.len().min(len);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+fn shp_header(buf: &mut Vec<u8>, length: usize, bbox: [f64; 4]) {
+    buf.extend_from_slice(&9994i32.to_be_bytes());
+    buf.extend_from_slice(&[0; 20]);
+    buf.extend_from_slice(&((length / 2) as i32).to_be_bytes());
+    buf.extend_from_slice(&1000i32.to_le_bytes());
+    buf.extend_from_slice(&SHAPE_POLYGON.to_le_bytes());
+    for x in bbox {
+        buf.extend_from_slice(&x.to_le_bytes());
+    }
+    // Z and M ranges unused
+    buf.extend_from_slice(&[0; 32]);
+}
+
+// Polygon record content, exterior ring clockwise
+fn shp_polygon(polygon: &Polygon<f64>) -> (Vec<u8>, [f64; 4]) {
+    let polygon = polygon.orient(Direction::Reversed);
+    let ring = polygon.exterior();
+    let bbox = polygon
+        .bounding_rect()
+        .map_or([0.0; 4], |r| [r.min().x, r.min().y, r.max().x, r.max().y]);
+
+    let mut buf = vec![];
+    buf.extend_from_slice(&SHAPE_POLYGON.to_le_bytes());
+    for x in bbox {
+        buf.extend_from_slice(&x.to_le_bytes());
+    }
+    buf.extend_from_slice(&1i32.to_le_bytes());
+    buf.extend_from_slice(&(ring.0.len() as i32).to_le_bytes());
+    buf.extend_from_slice(&0i32.to_le_bytes());
+    for c in ring.coords() {
+        buf.extend_from_slice(&c.x.to_le_bytes());
+        buf.extend_from_slice(&c.y.to_le_bytes());
+    }
+    (buf, bbox)
+}
+
+fn dbf_header(buf: &mut Vec<u8>, date: &str, records: usize) {
+    let record_len = 1 + FIELDS.iter().map(|f| f.2).sum::<usize>();
+    let header_len = 32 + 32 * FIELDS.len() + 1;
+
+    // Version and date of last update (YYMMDD)
+    let date_part = |r: std::ops::Range<usize>, offset: u16| {
+        date.get(r)
+            .and_then(|x| x.parse::<u16>().ok())
+            .map_or(1, |x| x.saturating_sub(offset) as u8)
+    };
+    buf.push(0x03);
+    buf.push(date_part(0..4, 1900));
+    buf.push(date_part(5..7, 0));
+    buf.push(date_part(8..10, 0));
+
+    buf.extend_from_slice(&(records as u32).to_le_bytes());
+    buf.extend_from_slice(&(header_len as u16).to_le_bytes());
+    buf.extend_from_slice(&(record_len as u16).to_le_bytes());
+    buf.extend_from_slice(&[0; 20]);
+
+    for (name, field_type, len, decimals) in FIELDS {
+        let mut field_name = name.as_bytes().to_vec();
+        field_name.resize(11, 0);
+        buf.extend_from_slice(&field_name);
+        buf.push(field_type);
+        buf.extend_from_slice(&[0; 4]);
+        buf.push(len as u8);
+        buf.push(decimals);
+        buf.extend_from_slice(&[0; 14]);
+    }
+    buf.push(0x0d);
+}
+
+fn dbf_record(buf: &mut Vec<u8>, values: &[String]) {
+    buf.push(b' ');
+    for ((_, field_type, len, _), value) in FIELDS.iter().zip(values) {
+        let value = field_text(value, *len);
+        let field = if *field_type == b'N' {
+            format!("{:>width$}", value, width = len)
+        } else {
+            format!("{:<width$}", value, width = len)
+        };
+        // Pad by bytes, multi-byte characters shorten the text
+        let mut bytes = field.into_bytes();
+        bytes.truncate(*len);
+        bytes.resize(*len, b' ');
+        buf.extend_from_slice(&bytes);
+    }
+}
+
+// CRC-32 (IEEE) for zip entries
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+// Uncompressed zip archive
+fn zip(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+    // 1980-01-01 00:00, DOS format
+    const DOS_TIME: u16 = 0;
+    const DOS_DATE: u16 = 0x21;
+
+    let mut buf = vec![];
+    let mut central = vec![];
+    for (name, data) in files {
+        let crc = crc32(data);
+        let offset = buf.len() as u32;
+
+        let mut common = vec![];
+        common.extend_from_slice(&10u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&DOS_TIME.to_le_bytes());
+        common.extend_from_slice(&DOS_DATE.to_le_bytes());
+        common.extend_from_slice(&crc.to_le_bytes());
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+
+        // Local header and data
+        buf.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        buf.extend_from_slice(&common);
+        buf.extend_from_slice(name.as_bytes());
+        buf.extend_from_slice(data);
+
+        // Central directory entry
+        central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes());
+        central.extend_from_slice(&common);
+        // Comment length, disk, internal and external attributes
+        central.extend_from_slice(&[0; 10]);
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = buf.len() as u32;
+    buf.extend_from_slice(&central);
+
+    // End of central directory
+    buf.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    buf.extend_from_slice(&[0; 4]);
+    buf.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    buf.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    buf.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&central_offset.to_le_bytes());
+    buf.extend_from_slice(&0u16.to_le_bytes());
+    buf
+}
+
+// Generate zipped shapefile, files named <basename>.shp etc.
+pub fn shapefile(yaixm: &Yaixm, settings: &Settings, basename: &str) -> Vec<u8> {
+    let airspace = select_airspace(yaixm, settings);
+
+    let mut records = vec![];
+    let mut attributes = vec![];
+    let mut bbox = [f64::MAX, f64::MAX, f64::MIN, f64::MIN];
+    for sel in select_volumes(&airspace, settings) {
+        let polygon = volume_polygon(sel.volume, DEFAULT_TOLERANCE);
+        if polygon.exterior().0.is_empty() {
+            continue;
+        }
+
+        let (record, rbox) = shp_polygon(&polygon);
+        bbox = [
+            bbox[0].min(rbox[0]),
+            bbox[1].min(rbox[1]),
+            bbox[2].max(rbox[2]),
+            bbox[3].max(rbox[3]),
+        ];
+        records.push(record);
+
+        attributes.push(vec![
+            sel.name(settings),
+            sel.airtype.to_string(),
+            sel.icao_class()
+                .map_or("".to_string(), |c| c.as_str().to_string()),
+            sel.volume
+                .lower
+                .feet()
+                .map_or("".to_string(), |ft| ft.to_string()),
+            sel.volume
+                .upper
+                .feet()
+                .map_or("".to_string(), |ft| ft.to_string()),
+            sel.volume
+                .frequency
+                .map_or("".to_string(), |f| format!("{:.3}", f)),
+            sel.rules().join(","),
+        ]);
+    }
+    if records.is_empty() {
+        bbox = [0.0; 4];
+    }
+
+    // Main file and index
+    let shp_len = 100 + records.iter().map(|r| 8 + r.len()).sum::<usize>();
+    let mut shp = vec![];
+    let mut shx = vec![];
+    shp_header(&mut shp, shp_len, bbox);
+    shp_header(&mut shx, 100 + 8 * records.len(), bbox);
+    for (n, record) in records.iter().enumerate() {
+        shx.extend_from_slice(&((shp.len() / 2) as i32).to_be_bytes());
+        shx.extend_from_slice(&((record.len() / 2) as i32).to_be_bytes());
+
+        shp.extend_from_slice(&(n as i32 + 1).to_be_bytes());
+        shp.extend_from_slice(&((record.len() / 2) as i32).to_be_bytes());
+        shp.extend_from_slice(record);
+    }
+
+    // Attributes
+    let mut dbf = vec![];
+    dbf_header(&mut dbf, &yaixm.release.airac_date, attributes.len());
+    for values in &attributes {
+        dbf_record(&mut dbf, values);
+    }
+    dbf.push(0x1a);
+
+    zip(&[
+        (format!("{}.shp", basename), shp),
+        (format!("{}.shx", basename), shx),
+        (format!("{}.dbf", basename), dbf),
+        (format!("{}.prj", basename), PRJ.as_bytes().to_vec()),
+        (format!("{}.cpg", basename), b"UTF-8".to_vec()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaixm::from_json;
+
+    fn u16_le(buf: &[u8], n: usize) -> usize {
+        u16::from_le_bytes(buf[n..n + 2].try_into().unwrap()) as usize
+    }
+
+    fn u32_le(buf: &[u8], n: usize) -> usize {
+        u32::from_le_bytes(buf[n..n + 4].try_into().unwrap()) as usize
+    }
+
+    fn i32_be(buf: &[u8], n: usize) -> usize {
+        i32::from_be_bytes(buf[n..n + 4].try_into().unwrap()) as usize
+    }
+
+    // Files from zip local headers, checking sizes and CRCs
+    fn unzip(buf: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let mut files = vec![];
+        let mut n = 0;
+        while u32_le(buf, n) == 0x04034b50 {
+            let (crc, size) = (u32_le(buf, n + 14), u32_le(buf, n + 18));
+            assert_eq!(size, u32_le(buf, n + 22));
+            let name_len = u16_le(buf, n + 26);
+            let name = String::from_utf8(buf[n + 30..n + 30 + name_len].to_vec()).unwrap();
+            let data = buf[n + 30 + name_len..n + 30 + name_len + size].to_vec();
+            assert_eq!(crc, crc32(&data) as usize);
+            n += 30 + name_len + size;
+            files.push((name, data));
+        }
+
+        // End of central directory
+        let eocd = buf.len() - 22;
+        assert_eq!(u32_le(buf, eocd), 0x06054b50);
+        assert_eq!(u16_le(buf, eocd + 10), files.len());
+        assert_eq!(u32_le(buf, eocd + 16), n);
+        assert_eq!(n + u32_le(buf, eocd + 12), eocd);
+        files
+    }
+
+    #[test]
+    fn crc32_check() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn dbf_field_bytes() {
+        let mut buf = vec![];
+        let mut values = vec!["".to_string(); FIELDS.len()];
+        values[0] = "é".repeat(60);
+        values[3] = "2500".to_string();
+        dbf_record(&mut buf, &values);
+        assert_eq!(buf.len(), 1 + FIELDS.iter().map(|f| f.2).sum::<usize>());
+        assert_eq!(&buf[118..124], b"  2500");
+    }
+
+    #[test]
+    fn shapefile_lengths() {
+        let yaixm = from_json(include_str!("../tests/data/square.json")).unwrap();
+        let files = unzip(&shapefile(&yaixm, &Settings::default(), "test"));
+        assert_eq!(
+            files.iter().map(|f| f.0.as_str()).collect::<Vec<&str>>(),
+            ["test.shp", "test.shx", "test.dbf", "test.prj", "test.cpg"]
+        );
+        let (shp, shx, dbf) = (&files[0].1, &files[1].1, &files[2].1);
+
+        // File lengths in 16 bit words
+        assert_eq!(i32_be(shp, 0), 9994);
+        assert_eq!(i32_be(shp, 24) * 2, shp.len());
+        assert_eq!(i32_be(shx, 24) * 2, shx.len());
+
+        // Records match index
+        let mut offset = 100;
+        for (n, index) in shx[100..].chunks(8).enumerate() {
+            assert_eq!(i32_be(index, 0) * 2, offset);
+            assert_eq!(i32_be(shp, offset), n + 1);
+            let len = i32_be(shp, offset + 4) * 2;
+            assert_eq!(len, i32_be(index, 4) * 2);
+            offset += 8 + len;
+        }
+        assert_eq!(offset, shp.len());
+        assert_eq!(shx.len(), 100 + 8);
+
+        // Header, records and end of file marker
+        let (records, header_len, record_len) = (u32_le(dbf, 4), u16_le(dbf, 8), u16_le(dbf, 10));
+        assert_eq!(records, 1);
+        assert_eq!(header_len, 32 + 32 * FIELDS.len() + 1);
+        assert_eq!(dbf[header_len - 1], 0x0d);
+        assert_eq!(dbf.len(), header_len + records * record_len + 1);
+        assert_eq!(dbf[dbf.len() - 1], 0x1a);
+    }
+}
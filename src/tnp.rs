@@ -0,0 +1,228 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::convert::{format_distance, header, parse_openair, select_airspace, select_volumes};
+use crate::settings::{AirType, Overlay, Settings};
+use crate::yaixm::{Boundary, LatLon, Level, Yaixm};
+use std::fmt::Write;
+
+// TNP airspace type
+fn tnp_type(airtype: AirType) -> &'static str {
+    match airtype {
+        AirType::ClassA
+        | AirType::ClassB
+        | AirType::ClassC
+        | AirType::ClassD
+        | AirType::ClassE
+        | AirType::ClassF
+        | AirType::Cta
+        | AirType::Ctr => "CTA/CTR",
+        AirType::Danger => "DANGER",
+        AirType::Gliding => "GSEC",
+        AirType::Matz => "MATZ",
+        AirType::ClassG | AirType::Other => "OTHER",
+        AirType::Prohibited => "PROHIBITED",
+        AirType::Restricted => "RESTRICTED",
+        AirType::Rmz => "RMZ",
+        AirType::Tmz => "TMZ",
+    }
+}
+
+// TNP level format, SFC and flight levels as for OpenAir
+fn format_level(level: &Level) -> String {
+    match level {
+        Level::Altitude(ft) => format!("{}ALT", ft),
+        Level::Height(ft) => format!("{}AGL", ft),
+        Level::Unlimited => "UNLTD".to_string(),
+        _ => level.to_string(),
+    }
+}
+
+// TNP lat/lon format, e.g. N512345 W0012345
+fn format_latlon(latlon: &LatLon) -> String {
+    let (lat, lon) = (latlon.lat.abs(), latlon.lon.abs());
+    format!(
+        "{}{:02}{:02}{:02} {}{:03}{:02}{:02}",
+        if latlon.lat < 0 { "S" } else { "N" },
+        lat / 3600,
+        (lat / 60) % 60,
+        lat % 60,
+        if latlon.lon < 0 { "W" } else { "E" },
+        lon / 3600,
+        (lon / 60) % 60,
+        lon % 60,
+    )
+}
+
+fn do_boundary(out: &mut String, boundary: &[Boundary]) {
+    let mut first = None;
+    let mut prev = None;
+
+    for segment in boundary {
+        match segment {
+            Boundary::Line(line) => {
+                for point in line {
+                    let _ = writeln!(out, "POINT={}", format_latlon(point));
+                }
+                first = first.or(line.first());
+                prev = line.last();
+            }
+            Boundary::Arc(arc) => {
+                let _ = writeln!(
+                    out,
+                    "{} RADIUS={} CENTRE={} TO={}",
+                    if arc.dir == "cw" {
+                        "CLOCKWISE"
+                    } else {
+                        "ANTI-CLOCKWISE"
+                    },
+                    format_distance(&arc.radius),
+                    format_latlon(&arc.centre),
+                    format_latlon(&arc.to)
+                );
+                prev = Some(&arc.to);
+            }
+            Boundary::Circle(circle) => {
+                let _ = writeln!(
+                    out,
+                    "CIRCLE RADIUS={} CENTRE={}",
+                    format_distance(&circle.radius),
+                    format_latlon(&circle.centre)
+                );
+            }
+        }
+    }
+
+    // Close the polygon
+    if let Some(first) = first {
+        if Some(first) != prev {
+            let _ = writeln!(out, "POINT={}", format_latlon(first));
+        }
+    }
+}
+
+// Generate Tim Newport-Peace (SUA) data, with optional OpenAir overlay
+pub fn tnp(yaixm: &Yaixm, settings: &Settings, overlay: Option<&str>, user_agent: &str) -> String {
+    let mut airspace = if settings.overlay == Some(Overlay::AtzDzOnly) {
+        // Overlay only, no airspace
+        vec![]
+    } else {
+        select_airspace(yaixm, settings)
+    };
+
+    // Same header as OpenAir, with TNP comments
+    let rel = &yaixm.release;
+    let mut output = header(
+        &rel.note,
+        &rel.airac_date,
+        &rel.commit,
+        user_agent,
+        settings,
+    )
+    .lines()
+    .map(|line| line.replacen('*', "#", 1) + "\n")
+    .collect::<String>();
+    output.push_str("#\nINCLUDE=YES\n");
+
+    // Overlay features are filtered and typed as for other airspace
+    if let Some(overlay) = overlay {
+        match parse_openair(overlay) {
+            Ok(features) => airspace.extend(features),
+            Err(err) => {
+                let _ = writeln!(output, "# Invalid overlay data, {}", err);
+            }
+        }
+    }
+
+    for sel in select_volumes(&airspace, settings) {
+        output.push_str("#\n");
+        let _ = writeln!(output, "TITLE={}", sel.name(settings));
+        let _ = writeln!(output, "TYPE={}", tnp_type(sel.airtype));
+        if let Some(class) = sel.icao_class() {
+            let _ = writeln!(output, "CLASS={}", class.as_str());
+        }
+        if let Some(freq) = sel.volume.frequency {
+            let _ = writeln!(output, "RADIO={:.3}", freq);
+        }
+        let _ = writeln!(output, "TOPS={}", format_level(&sel.volume.upper));
+        let _ = writeln!(output, "BASE={}", format_level(&sel.volume.lower));
+        do_boundary(&mut output, &sel.volume.boundary);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Mode;
+    use crate::yaixm::from_json;
+
+    #[test]
+    fn levels() {
+        assert_eq!(format_level(&Level::Sfc), "SFC");
+        assert_eq!(format_level(&Level::Altitude(2500)), "2500ALT");
+        assert_eq!(format_level(&Level::Height(1500)), "1500AGL");
+        assert_eq!(format_level(&Level::Fl(65)), "FL65");
+        assert_eq!(format_level(&Level::Unlimited), "UNLTD");
+    }
+
+    #[test]
+    fn rat_only() {
+        let mut json: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/data/square.json")).unwrap();
+        let mut rat = json["airspace"][0].clone();
+        rat["name"] = "TEST RAT".into();
+        rat["id"] = "rat".into();
+        json["rat"] = serde_json::json!([rat]);
+        let yaixm = from_json(&json.to_string()).unwrap();
+
+        let mut settings = Settings::default();
+        settings.rat.insert("TEST RAT".to_string());
+        assert!(tnp(&yaixm, &settings, None, "").contains("TITLE=SQUARE"));
+
+        settings.mode = Mode::RatOnly;
+        let output = tnp(&yaixm, &settings, None, "");
+        assert!(!output.contains("TITLE=SQUARE"));
+        assert!(output.contains("TITLE=TEST RAT"));
+    }
+
+    #[test]
+    fn overlay() {
+        let yaixm = from_json(include_str!("../tests/data/square.json")).unwrap();
+        let overlay = "AC CTR\nAN TEST ATZ\nAL SFC\nAH 2000ft\nV X=51:05N 000:55W\nDC 2\n";
+
+        let mut settings = Settings {
+            overlay: Some(Overlay::AtzDz),
+            ..Default::default()
+        };
+        let output = tnp(&yaixm, &settings, Some(overlay), "");
+        assert!(output.contains("TITLE=SQUARE"));
+        assert!(output.contains("TITLE=TEST"));
+        assert!(output.contains("CIRCLE RADIUS=2"));
+
+        settings.overlay = Some(Overlay::AtzDzOnly);
+        let output = tnp(&yaixm, &settings, Some(overlay), "");
+        assert!(!output.contains("TITLE=SQUARE"));
+        assert!(output.contains("TITLE=TEST"));
+
+        // Overlay filtered by maximum level
+        settings.max_level = 10;
+        let overlay = overlay
+            .replace("AL SFC", "AL FL100")
+            .replace("2000ft", "FL150");
+        let output = tnp(&yaixm, &settings, Some(&overlay), "");
+        assert!(!output.contains("TITLE=TEST"));
+    }
+}
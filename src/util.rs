@@ -0,0 +1,58 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::settings::AirType;
+
+// Escape text for XML
+pub fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Default display colour (#rrggbb) for airspace type
+pub fn airtype_colour(airtype: AirType) -> &'static str {
+    match airtype {
+        AirType::ClassA => "#d62728",
+        AirType::ClassB => "#d62728",
+        AirType::ClassC => "#1f77b4",
+        AirType::ClassD => "#1f77b4",
+        AirType::ClassE => "#2ca02c",
+        AirType::ClassF => "#7f7f7f",
+        AirType::ClassG => "#7f7f7f",
+        AirType::Danger => "#ff7f0e",
+        AirType::Cta => "#1f77b4",
+        AirType::Ctr => "#1f77b4",
+        AirType::Gliding => "#17becf",
+        AirType::Matz => "#9467bd",
+        AirType::Other => "#8c564b",
+        AirType::Prohibited => "#d62728",
+        AirType::Restricted => "#d62728",
+        AirType::Rmz => "#bcbd22",
+        AirType::Tmz => "#e377c2",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape() {
+        assert_eq!(xml_escape("<A & \"B\">"), "&lt;A &amp; &quot;B&quot;&gt;");
+        assert_eq!(xml_escape("PLAIN"), "PLAIN");
+    }
+}
@@ -0,0 +1,192 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::geometry::feature_centre;
+use crate::yaixm::{IcaoType, LatLon, LocalType, Yaixm};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+// SeeYou waypoint styles
+const STYLE_AIRFIELD: u8 = 2;
+const STYLE_GLIDING: u8 = 4;
+const STYLE_TRANSMITTER: u8 = 8;
+
+// Maximum length of waypoint code
+const CODE_LEN: usize = 6;
+
+// Quoted CSV field
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+// Short waypoint code from name, made unique with a numeric suffix
+fn short_code(name: &str, used: &mut HashSet<String>) -> String {
+    let base = name
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .take(CODE_LEN)
+        .collect::<String>();
+
+    let mut code = base.clone();
+    let mut n = 1;
+    while used.contains(&code) {
+        n += 1;
+        let suffix = n.to_string();
+        code = base
+            .chars()
+            .take(CODE_LEN.saturating_sub(suffix.len()))
+            .collect::<String>()
+            + &suffix;
+    }
+    used.insert(code.clone());
+    code
+}
+
+// SeeYou lat/lon format, e.g. 5123.450N,00123.450W
+fn format_latlon(latlon: &LatLon) -> String {
+    let (lat, lon) = (latlon.lat.abs(), latlon.lon.abs());
+    format!(
+        "{:02}{:06.3}{},{:03}{:06.3}{}",
+        lat / 3600,
+        f64::from(lat % 3600) / 60.0,
+        if latlon.lat < 0 { "S" } else { "N" },
+        lon / 3600,
+        f64::from(lon % 3600) / 60.0,
+        if latlon.lon < 0 { "W" } else { "E" }
+    )
+}
+
+// Generate SeeYou .cup file of gliding sites, airfields and obstacles
+pub fn waypoints(yaixm: &Yaixm) -> String {
+    // Frequency for each airspace id
+    let mut services = HashMap::new();
+    for service in &yaixm.service {
+        for id in &service.controls {
+            services.entry(id.as_str()).or_insert(service);
+        }
+    }
+
+    let mut codes = HashSet::new();
+    let mut out = "name,code,country,lat,lon,elev,style,rwdir,rwlen,freq,desc\n".to_string();
+
+    for feature in &yaixm.airspace {
+        let (style, desc) = match (&feature.icao_type, feature.local_type) {
+            (IcaoType::Other, Some(LocalType::Glider)) => (STYLE_GLIDING, "Gliding site"),
+            (_, Some(LocalType::Ul)) => (STYLE_AIRFIELD, "Microlight airfield"),
+            (_, Some(LocalType::NoAtz)) => (STYLE_AIRFIELD, "Airfield"),
+            _ => continue,
+        };
+        let Some(position) = feature_centre(feature) else {
+            continue;
+        };
+
+        // Service for feature, or for any of its volumes
+        let service = feature
+            .id
+            .iter()
+            .chain(feature.geometry.iter().filter_map(|v| v.id.as_ref()))
+            .find_map(|id| services.get(id.as_str()));
+        let (freq, desc) = match service {
+            Some(s) => (
+                format!("{:.3}", s.frequency),
+                format!("{} ({})", desc, s.callsign),
+            ),
+            None => ("".to_string(), desc.to_string()),
+        };
+
+        let _ = writeln!(
+            out,
+            "{},{},GB,{},,{},,,{},{}",
+            quote(&feature.name),
+            quote(&short_code(&feature.name, &mut codes)),
+            format_latlon(&position),
+            style,
+            freq,
+            quote(&desc)
+        );
+    }
+
+    for obstacle in &yaixm.obstacle {
+        let elev = obstacle
+            .elevation
+            .feet()
+            .map_or("".to_string(), |ft| format!("{}ft", ft));
+        let _ = writeln!(
+            out,
+            "{},{},GB,{},{},{},,,,{}",
+            quote(&obstacle.name),
+            quote(&short_code(&obstacle.name, &mut codes)),
+            format_latlon(&obstacle.position),
+            elev,
+            STYLE_TRANSMITTER,
+            quote("Obstacle")
+        );
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaixm::from_json;
+    use serde_json::json;
+
+    #[test]
+    fn codes() {
+        let mut used = HashSet::new();
+        assert_eq!(short_code("Lasham Gliding Society", &mut used), "LASHAM");
+        assert_eq!(short_code("Lasham", &mut used), "LASHA2");
+        assert_eq!(short_code("LASHAM", &mut used), "LASHA3");
+        assert_eq!(short_code("St. Ives", &mut used), "STIVES");
+    }
+
+    #[test]
+    fn cup_rows() {
+        let mut json: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/data/square.json")).unwrap();
+        let mut site = json["airspace"][0].clone();
+        site["id"] = "site".into();
+        site["name"] = "TEST \"GLIDING\" SITE".into();
+        site["type"] = "OTHER".into();
+        site["localtype"] = "GLIDER".into();
+        site["geometry"][0]["id"] = "site-1".into();
+        site["geometry"][0]["boundary"] =
+            json!([{"circle": {"centre": "520000N 0010000W", "radius": "2 nm"}}]);
+        json["airspace"].as_array_mut().unwrap().push(site);
+        json["service"].as_array_mut().unwrap().push(
+            json!({"callsign": "TEST GLIDING", "frequency": 129.975, "controls": ["site-1"]}),
+        );
+        json["obstacle"] = json!([{
+            "name": "TEST MAST",
+            "elevation": "1200 ft",
+            "position": "513000N 0011500W"
+        }]);
+        let yaixm = from_json(&json.to_string()).unwrap();
+
+        let output = waypoints(&yaixm);
+        let rows = output.lines().collect::<Vec<&str>>();
+        assert_eq!(
+            rows,
+            [
+                "name,code,country,lat,lon,elev,style,rwdir,rwlen,freq,desc",
+                "\"TEST \"\"GLIDING\"\" SITE\",\"TESTGL\",GB,5200.000N,00100.000W,,4,,,129.975,\
+                 \"Gliding site (TEST GLIDING)\"",
+                "\"TEST MAST\",\"TESTMA\",GB,5130.000N,00115.000W,1200ft,8,,,,\"Obstacle\"",
+            ]
+        );
+    }
+}
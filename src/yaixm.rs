@@ -13,7 +13,15 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 //
+use crate::settings::AirType;
+use chrono::Utc;
+use geo::{Distance as _, Geodesic, Point};
 use serde::Deserialize;
+use serde_json::{json, Value};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
 
 #[derive(Clone, Copy, Deserialize, Debug, PartialEq)]
 pub enum IcaoClass {
@@ -99,42 +107,347 @@ pub enum Rule {
     Tmz,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+// Position, stored as whole seconds of arc (north and east positive)
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq)]
+#[serde(try_from = "String")]
+pub struct LatLon {
+    pub lat: i32,
+    pub lon: i32,
+}
+
+impl LatLon {
+    pub fn from_degrees(lat: f64, lon: f64) -> Self {
+        LatLon {
+            lat: (lat * 3600.0).round() as i32,
+            lon: (lon * 3600.0).round() as i32,
+        }
+    }
+
+    pub fn lat_degrees(&self) -> f64 {
+        f64::from(self.lat) / 3600.0
+    }
+
+    pub fn lon_degrees(&self) -> f64 {
+        f64::from(self.lon) / 3600.0
+    }
+
+    // Geo point, x = longitude, y = latitude
+    pub fn point(&self) -> Point<f64> {
+        Point::new(self.lon_degrees(), self.lat_degrees())
+    }
+}
+
+// Parse DDMMSS[NS] or DDDMMSS[EW] to signed seconds
+fn parse_dms(value: &str, deg_len: usize, max_deg: i32, hemi: [char; 2]) -> Option<i32> {
+    if value.len() != deg_len + 5 || !value.is_ascii() {
+        return None;
+    }
+
+    let (digits, h) = value.split_at(deg_len + 4);
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let deg: i32 = digits[..deg_len].parse().ok()?;
+    let min: i32 = digits[deg_len..deg_len + 2].parse().ok()?;
+    let sec: i32 = digits[deg_len + 2..].parse().ok()?;
+    if min >= 60 || sec >= 60 {
+        return None;
+    }
+
+    let secs = deg * 3600 + min * 60 + sec;
+    if secs > max_deg * 3600 {
+        return None;
+    }
+
+    match h.chars().next() {
+        Some(c) if c == hemi[0] => Some(secs),
+        Some(c) if c == hemi[1] => Some(-secs),
+        _ => None,
+    }
+}
+
+impl FromStr for LatLon {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("invalid lat/lon \"{}\"", s);
+
+        let mut parts = s.split_whitespace();
+        let (Some(lat), Some(lon), None) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(err());
+        };
+
+        Ok(LatLon {
+            lat: parse_dms(lat, 2, 90, ['N', 'S']).ok_or_else(err)?,
+            lon: parse_dms(lon, 3, 180, ['E', 'W']).ok_or_else(err)?,
+        })
+    }
+}
+
+impl TryFrom<String> for LatLon {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for LatLon {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (lat, lon) = (self.lat.abs(), self.lon.abs());
+        write!(
+            f,
+            "{:02}{:02}{:02}{} {:03}{:02}{:02}{}",
+            lat / 3600,
+            (lat / 60) % 60,
+            lat % 60,
+            if self.lat < 0 { 'S' } else { 'N' },
+            lon / 3600,
+            (lon / 60) % 60,
+            lon % 60,
+            if self.lon < 0 { 'W' } else { 'E' }
+        )
+    }
+}
+
+// Transition altitude (feet) - flight levels are always above this
+pub const TRANSITION_ALTITUDE: u32 = 3000;
+
+// Vertical level
+#[derive(Clone, Copy, Deserialize, Debug, Eq, PartialEq)]
+#[serde(try_from = "String")]
+pub enum Level {
+    Sfc,
+    // Feet above mean sea level
+    Altitude(u32),
+    // Feet above ground level
+    Height(u32),
+    Fl(u16),
+    Unlimited,
+}
+
+impl Level {
+    // Approximate height in feet (at standard pressure for flight levels,
+    // terrain ignored for heights). None if unlimited
+    pub fn feet(&self) -> Option<u32> {
+        match *self {
+            Level::Sfc => Some(0),
+            Level::Altitude(ft) | Level::Height(ft) => Some(ft),
+            Level::Fl(fl) => Some(u32::from(fl) * 100),
+            Level::Unlimited => None,
+        }
+    }
+
+    // Sort key. Altitudes at or below the transition altitude are below
+    // any flight level, otherwise compare at standard pressure
+    fn sort_key(&self) -> (u32, u8, u16) {
+        match *self {
+            Level::Sfc => (0, 0, 0),
+            Level::Altitude(ft) => (ft, 1, 0),
+            Level::Height(ft) => (ft, 2, 0),
+            Level::Fl(fl) => ((u32::from(fl) * 100).max(TRANSITION_ALTITUDE), 3, fl),
+            Level::Unlimited => (u32::MAX, 4, 0),
+        }
+    }
+}
+
+impl Ord for Level {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl PartialOrd for Level {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("invalid level \"{}\"", s);
+
+        if s == "SFC" {
+            Ok(Level::Sfc)
+        } else if s == "UNL" {
+            Ok(Level::Unlimited)
+        } else if let Some(fl) = s.strip_prefix("FL") {
+            fl.parse().map(Level::Fl).map_err(|_| err())
+        } else {
+            let mut parts = s.split_whitespace();
+            let ft = parts.next().and_then(|x| x.parse().ok()).ok_or_else(err)?;
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some("ft"), None, None) => Ok(Level::Altitude(ft)),
+                (Some("ft"), Some(agl), None) if agl.eq_ignore_ascii_case("agl") => {
+                    Ok(Level::Height(ft))
+                }
+                _ => Err(err()),
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for Level {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Level::Sfc => write!(f, "SFC"),
+            Level::Altitude(ft) => write!(f, "{} ft", ft),
+            Level::Height(ft) => write!(f, "{} ft AGL", ft),
+            Level::Fl(fl) => write!(f, "FL{}", fl),
+            Level::Unlimited => write!(f, "UNL"),
+        }
+    }
+}
+
+// Distance units
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DistanceUnit {
+    Nm,
+    Km,
+    M,
+    Ft,
+}
+
+impl DistanceUnit {
+    fn metres(&self) -> f64 {
+        match self {
+            DistanceUnit::Nm => 1852.0,
+            DistanceUnit::Km => 1000.0,
+            DistanceUnit::M => 1.0,
+            DistanceUnit::Ft => 0.3048,
+        }
+    }
+}
+
+// Distance with explicit units
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq)]
+#[serde(try_from = "String")]
+pub struct Distance {
+    pub value: f64,
+    pub unit: DistanceUnit,
+}
+
+impl Distance {
+    pub fn nm(value: f64) -> Self {
+        Distance {
+            value,
+            unit: DistanceUnit::Nm,
+        }
+    }
+
+    pub fn metres(&self) -> f64 {
+        self.value * self.unit.metres()
+    }
+
+    pub fn nautical_miles(&self) -> f64 {
+        self.metres() / DistanceUnit::Nm.metres()
+    }
+
+    pub fn kilometres(&self) -> f64 {
+        self.metres() / DistanceUnit::Km.metres()
+    }
+}
+
+impl FromStr for Distance {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("invalid distance \"{}\"", s);
+
+        let mut parts = s.split_whitespace();
+        let (Some(value), Some(unit), None) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(err());
+        };
+
+        let value: f64 = value.parse().map_err(|_| err())?;
+        if !value.is_finite() || value < 0.0 {
+            return Err(err());
+        }
+
+        let unit = match unit {
+            "nm" => DistanceUnit::Nm,
+            "km" => DistanceUnit::Km,
+            "m" => DistanceUnit::M,
+            "ft" => DistanceUnit::Ft,
+            _ => return Err(err()),
+        };
+
+        Ok(Distance { value, unit })
+    }
+}
+
+impl TryFrom<String> for Distance {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for Distance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unit = match self.unit {
+            DistanceUnit::Nm => "nm",
+            DistanceUnit::Km => "km",
+            DistanceUnit::M => "m",
+            DistanceUnit::Ft => "ft",
+        };
+        write!(f, "{} {}", self.value, unit)
+    }
+}
+
+#[derive(Clone, Deserialize, Debug, PartialEq)]
 pub struct Circle {
-    pub centre: String,
-    pub radius: String,
+    pub centre: LatLon,
+    pub radius: Distance,
 }
 
-#[derive(Clone, Deserialize, Debug)]
-#[allow(dead_code)]
+#[derive(Clone, Deserialize, Debug, PartialEq)]
 pub struct Arc {
-    pub centre: String,
+    pub centre: LatLon,
     pub dir: String,
-    pub radius: String,
-    pub to: String,
+    pub radius: Distance,
+    pub to: LatLon,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, PartialEq)]
 pub enum Boundary {
     #[serde(rename = "circle")]
     Circle(Circle),
     #[serde(rename = "arc")]
     Arc(Arc),
     #[serde(rename = "line")]
-    Line(Vec<String>),
+    Line(Vec<LatLon>),
 }
 
 #[derive(Clone, Deserialize, Debug)]
 pub struct Volume {
     pub id: Option<String>,
     pub name: Option<String>,
-    pub lower: String,
-    pub upper: String,
+    pub lower: Level,
+    pub upper: Level,
     #[serde(rename = "class")]
     pub icao_class: Option<IcaoClass>,
     pub rules: Option<Vec<Rule>>,
     pub seq: Option<String>,
     pub frequency: Option<f64>,
+    // Station callsign, from service data
+    #[serde(skip)]
+    pub callsign: Option<String>,
+    // Type from imported OpenAir data, where YAIXM has no equivalent
+    #[serde(skip)]
+    pub airtype: Option<AirType>,
     pub boundary: Vec<Boundary>,
 }
 
@@ -175,13 +488,12 @@ pub struct Loa {
 
 #[derive(Clone, Deserialize, Debug)]
 pub struct Obstacle {
-    pub elevation: String,
+    pub elevation: Level,
     pub name: String,
-    pub position: String,
+    pub position: LatLon,
 }
 
 #[derive(Clone, Deserialize, Debug)]
-#[allow(dead_code)]
 pub struct Service {
     pub callsign: String,
     pub frequency: f64,
@@ -193,21 +505,84 @@ pub struct Service {
 pub struct Release {
     pub airac_date: String,
     pub timestamp: String,
-    pub schema_version: u8,
+    pub schema_version: u64,
     pub note: String,
     pub commit: String,
 }
 
+impl Release {
+    // AIRAC date (YYYY-MM-DD), or whatever local data has instead
+    pub fn date(&self) -> &str {
+        self.airac_date.get(..10).unwrap_or(&self.airac_date)
+    }
+}
+
 #[derive(Clone, Deserialize, Debug)]
 pub struct Yaixm {
     pub airspace: Vec<Feature>,
+    // Other sections are in separate files in unreleased data
+    #[serde(default)]
     pub rat: Vec<Feature>,
+    #[serde(default)]
     pub loa: Vec<Loa>,
+    #[serde(default)]
     pub obstacle: Vec<Obstacle>,
+    #[serde(default)]
     pub service: Vec<Service>,
     pub release: Release,
 }
 
+// Data release listed in the manifest
+#[derive(Clone, Deserialize, Debug)]
+pub struct ManifestEntry {
+    pub airac_date: String,
+    pub path: String,
+}
+
+impl ManifestEntry {
+    pub fn date(&self) -> &str {
+        self.airac_date.get(..10).unwrap_or(&self.airac_date)
+    }
+}
+
+// List of available data releases
+#[derive(Clone, Deserialize, Debug)]
+pub struct Manifest {
+    pub releases: Vec<ManifestEntry>,
+}
+
+// AIRAC cycle selection
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cycle {
+    Current,
+    Next,
+}
+
+impl Manifest {
+    // Latest release in force on date (YYYY-MM-DD)
+    pub fn current(&self, date: &str) -> Option<&ManifestEntry> {
+        self.releases
+            .iter()
+            .filter(|r| r.date() <= date)
+            .max_by_key(|r| r.date())
+    }
+
+    // Earliest release not yet in force on date
+    pub fn next(&self, date: &str) -> Option<&ManifestEntry> {
+        self.releases
+            .iter()
+            .filter(|r| r.date() > date)
+            .min_by_key(|r| r.date())
+    }
+
+    pub fn release(&self, cycle: Cycle, date: &str) -> Option<&ManifestEntry> {
+        match cycle {
+            Cycle::Current => self.current(date),
+            Cycle::Next => self.next(date),
+        }
+    }
+}
+
 // List of gliding sites
 pub fn gliding_sites(yaixm: &Yaixm) -> Vec<String> {
     yaixm
@@ -243,37 +618,462 @@ pub fn wave_names(yaixm: &Yaixm) -> Vec<String> {
         .collect::<Vec<String>>()
 }
 
-// Convert lat/lon string to floating point degrees
-pub fn latlon_to_degrees(latlon: &str) -> (f64, f64) {
-    let bytes = latlon.as_bytes();
+// Airspace data file format
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DataFormat {
+    Json,
+    Yaml,
+    Aixm,
+}
+
+impl DataFormat {
+    // Guess format from content type, falling back to file name extension
+    pub fn detect(content_type: Option<&str>, name: &str) -> Self {
+        let content_type = content_type.unwrap_or_default().to_ascii_lowercase();
+        let name = name.to_ascii_lowercase();
+
+        if content_type.contains("yaml") {
+            DataFormat::Yaml
+        } else if content_type.contains("json") {
+            DataFormat::Json
+        } else if content_type.contains("xml") {
+            DataFormat::Aixm
+        } else if name.ends_with(".yaml") || name.ends_with(".yml") {
+            DataFormat::Yaml
+        } else if name.ends_with(".xml") {
+            DataFormat::Aixm
+        } else {
+            DataFormat::Json
+        }
+    }
+}
+
+// Parse YAIXM text in given format
+pub fn parse(text: &str, format: DataFormat) -> Result<Yaixm, String> {
+    match format {
+        DataFormat::Json => from_json(text),
+        DataFormat::Yaml => from_yaml(text),
+        DataFormat::Aixm => crate::aixm::from_aixm(text),
+    }
+}
+
+// Parse YAIXM from JSON text
+pub fn from_json(text: &str) -> Result<Yaixm, String> {
+    let value: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    from_value(value)
+}
+
+// Parse YAIXM from YAML text, which may have several documents
+pub fn from_yaml(text: &str) -> Result<Yaixm, String> {
+    from_yaml_files(&[text])
+}
+
+// Parse YAIXM from separate YAML files (airspace.yaml, loa.yaml, etc.),
+// merging their top level sections
+pub fn from_yaml_files(texts: &[&str]) -> Result<Yaixm, String> {
+    let mut merged = serde_json::Map::new();
+    for text in texts {
+        for document in serde_yaml::Deserializer::from_str(text) {
+            let value = Value::deserialize(document).map_err(|e| e.to_string())?;
+            let Value::Object(sections) = value else {
+                if value.is_null() {
+                    // Empty document
+                    continue;
+                }
+                return Err("YAML document is not a mapping".to_string());
+            };
+
+            for (key, section) in sections {
+                match (merged.get_mut(&key), section) {
+                    (None, section) => {
+                        merged.insert(key, section);
+                    }
+                    (Some(Value::Array(list)), Value::Array(mut more)) => list.append(&mut more),
+                    _ => return Err(format!("duplicate \"{}\" section", key)),
+                }
+            }
+        }
+    }
+
+    // Unreleased data doesn't have a release section
+    merged.entry("release").or_insert_with(|| {
+        let now = Utc::now().to_rfc3339();
+        json!({
+            "airac_date": now,
+            "timestamp": now,
+            "schema_version": SCHEMA_VERSION,
+            "note": "Unreleased data",
+            "commit": "unreleased",
+        })
+    });
+
+    from_value(Value::Object(merged))
+}
+
+fn from_value(mut value: Value) -> Result<Yaixm, String> {
+    migrate(&mut value)?;
+    Yaixm::deserialize(&value).map_err(|err| find_bad_feature(&value).unwrap_or(err.to_string()))
+}
+
+// Current schema version, older versions are migrated on load
+pub const SCHEMA_VERSION: u64 = 1;
+
+// Check schema version and upgrade older data to the current schema.
+// Data released before the schema was versioned is version 0
+fn migrate(value: &mut Value) -> Result<(), String> {
+    if !value["release"].is_object() {
+        return Err("missing release section".to_string());
+    }
+    let version = match &value["release"]["schema_version"] {
+        Value::Null => 0,
+        version => version.as_u64().ok_or("invalid release schema version")?,
+    };
+
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "airspace data schema version {} is newer than supported version {}",
+            version, SCHEMA_VERSION
+        ));
+    }
+
+    for from in version..SCHEMA_VERSION {
+        match from {
+            0 => migrate_v0(value),
+            _ => return Err(format!("can't migrate schema version {}", from)),
+        }
+    }
+    value["release"]["schema_version"] = SCHEMA_VERSION.into();
+
+    Ok(())
+}
+
+// Version 0 data may omit the RAT, LOA, obstacle and service lists
+fn migrate_v0(value: &mut Value) {
+    if let Some(obj) = value.as_object_mut() {
+        for key in ["rat", "loa", "obstacle", "service"] {
+            obj.entry(key).or_insert_with(|| Value::Array(Vec::new()));
+        }
+    }
+}
+
+// Re-parse features one by one to find the one that caused an error
+fn find_bad_feature(value: &Value) -> Option<String> {
+    let name = |v: &Value, key: &str| v[key].as_str().unwrap_or("unnamed").to_string();
+
+    let loa_areas = value["loa"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|loa| loa["areas"].as_array().into_iter().flatten());
+
+    let features = ["airspace", "rat"]
+        .iter()
+        .flat_map(|key| value[key].as_array().into_iter().flatten())
+        .chain(
+            loa_areas
+                .clone()
+                .flat_map(|area| area["add"].as_array().into_iter().flatten()),
+        );
+    for feature in features {
+        if let Err(err) = Feature::deserialize(feature) {
+            return Some(format!("Feature \"{}\": {}", name(feature, "name"), err));
+        }
+    }
+
+    let replacements = loa_areas.flat_map(|area| area["replace"].as_array().into_iter().flatten());
+    for replace in replacements {
+        if let Err(err) = Replace::deserialize(replace) {
+            return Some(format!(
+                "LOA replacement \"{}\": {}",
+                name(replace, "id"),
+                err
+            ));
+        }
+    }
+
+    for obstacle in value["obstacle"].as_array().into_iter().flatten() {
+        if let Err(err) = Obstacle::deserialize(obstacle) {
+            return Some(format!("Obstacle \"{}\": {}", name(obstacle, "name"), err));
+        }
+    }
+
+    None
+}
+
+// Data problem found by validation
+#[derive(Clone, Debug, PartialEq)]
+pub struct Issue {
+    pub name: String,
+    pub message: String,
+}
 
-    let mut deg: f64 = latlon[0..2].parse().unwrap();
-    let mut min: f64 = latlon[2..4].parse().unwrap();
-    let mut sec: f64 = latlon[4..6].parse().unwrap();
-    let mut lat = deg + min / 60.0 + sec / 3600.0;
-    if bytes[6] == b'S' {
-        lat = -lat;
+impl Issue {
+    pub(crate) fn new(name: &str, message: String) -> Self {
+        Issue {
+            name: name.to_string(),
+            message,
+        }
     }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.message)
+    }
+}
+
+// Arc end point must be within this fraction (or 100m) of the radius
+pub(crate) const ARC_TOLERANCE: f64 = 0.02;
 
-    deg = latlon[8..11].parse().unwrap();
-    min = latlon[11..13].parse().unwrap();
-    sec = latlon[13..15].parse().unwrap();
-    let mut lon = deg + min / 60.0 + sec / 3600.0;
-    if bytes[15] == b'W' {
-        lon = -lon;
+fn check_volume(name: &str, volume: &Volume, issues: &mut Vec<Issue>) {
+    if let Some(Boundary::Arc(_)) = volume.boundary.first() {
+        issues.push(Issue::new(name, "boundary starts with an arc".to_string()));
     }
 
-    (lat, lon)
+    for segment in &volume.boundary {
+        if let Boundary::Arc(arc) = segment {
+            let dist = Geodesic::distance(arc.centre.point(), arc.to.point());
+            let radius = arc.radius.metres();
+            if (dist - radius).abs() > (radius * ARC_TOLERANCE).max(100.0) {
+                issues.push(Issue::new(
+                    name,
+                    format!(
+                        "arc end point {} is {:.0} m from centre, radius is {}",
+                        arc.to, dist, arc.radius
+                    ),
+                ));
+            }
+        }
+    }
 }
 
-// Convert radius to floating point metres
-pub fn radius_to_metres(radius: &str) -> f64 {
-    let parts = radius.split(" ").collect::<Vec<&str>>();
-    let dist: f64 = parts[0].parse().unwrap();
+// Check YAIXM data for errors
+pub fn validate(yaixm: &Yaixm) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    let loa_features = yaixm
+        .loa
+        .iter()
+        .flat_map(|loa| loa.areas.iter())
+        .flat_map(|area| area.add.iter());
+    let replacements = yaixm
+        .loa
+        .iter()
+        .flat_map(|loa| loa.areas.iter())
+        .flat_map(|area| area.replace.iter().flatten());
+
+    // Geometry and duplicate volume ids
+    let mut volume_ids = HashSet::new();
+    let mut feature_ids = HashSet::new();
+    for feature in yaixm
+        .airspace
+        .iter()
+        .chain(&yaixm.rat)
+        .chain(loa_features.clone())
+    {
+        if let Some(id) = &feature.id {
+            feature_ids.insert(id.as_str());
+        }
+
+        for volume in &feature.geometry {
+            check_volume(&feature.name, volume, &mut issues);
+
+            if let Some(id) = &volume.id {
+                if !volume_ids.insert(id.as_str()) {
+                    issues.push(Issue::new(
+                        &feature.name,
+                        format!("duplicate volume id \"{}\"", id),
+                    ));
+                }
+            }
+        }
+    }
+
+    for replace in replacements.clone() {
+        for volume in &replace.geometry {
+            check_volume(&replace.id, volume, &mut issues);
+        }
+    }
+
+    // LOA replacement volumes must exist in the main airspace or LOA additions
+    let replaceable = yaixm
+        .airspace
+        .iter()
+        .chain(loa_features)
+        .flat_map(|f| f.geometry.iter())
+        .filter_map(|v| v.id.as_deref())
+        .collect::<HashSet<&str>>();
+    for loa in &yaixm.loa {
+        for area in &loa.areas {
+            for replace in area.replace.iter().flatten() {
+                if !replaceable.contains(replace.id.as_str()) {
+                    issues.push(Issue::new(
+                        &loa.name,
+                        format!("replaced volume \"{}\" not found", replace.id),
+                    ));
+                }
+            }
+        }
+    }
+
+    // Replacement volumes can also be controlled
+    let volume_ids = volume_ids
+        .into_iter()
+        .chain(
+            replacements
+                .flat_map(|r| r.geometry.iter())
+                .filter_map(|v| v.id.as_deref()),
+        )
+        .collect::<HashSet<&str>>();
+
+    // Service ids
+    for service in &yaixm.service {
+        for id in &service.controls {
+            if !volume_ids.contains(id.as_str()) && !feature_ids.contains(id.as_str()) {
+                issues.push(Issue::new(
+                    &service.callsign,
+                    format!("controlled id \"{}\" not found", id),
+                ));
+            }
+        }
+    }
+
+    // RAT names are used as setting keys
+    let airspace_names = yaixm
+        .airspace
+        .iter()
+        .map(|f| f.name.as_str())
+        .collect::<HashSet<&str>>();
+    let mut rat_names = HashSet::new();
+    for rat in &yaixm.rat {
+        if !rat_names.insert(rat.name.as_str()) {
+            issues.push(Issue::new(&rat.name, "duplicate RAT name".to_string()));
+        }
+        if airspace_names.contains(rat.name.as_str()) {
+            issues.push(Issue::new(
+                &rat.name,
+                "RAT name clashes with airspace name".to_string(),
+            ));
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latlon_parse_format() {
+        let latlon: LatLon = "512345N 0012345W".parse().unwrap();
+        assert_eq!(latlon.lat, 51 * 3600 + 23 * 60 + 45);
+        assert_eq!(latlon.lon, -(3600 + 23 * 60 + 45));
+        assert_eq!(latlon.to_string(), "512345N 0012345W");
+
+        let latlon: LatLon = "000001S 1795959E".parse().unwrap();
+        assert_eq!(latlon.to_string(), "000001S 1795959E");
+    }
+
+    #[test]
+    fn latlon_parse_invalid() {
+        for s in [
+            "",
+            "512345N",
+            "512345N 0012345W x",
+            "516045N 0012345W",
+            "512345E 0012345W",
+            "912345N 0012345W",
+            "5123N 00123W",
+        ] {
+            assert!(s.parse::<LatLon>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn level_parse_format() {
+        for (s, level) in [
+            ("SFC", Level::Sfc),
+            ("UNL", Level::Unlimited),
+            ("FL65", Level::Fl(65)),
+            ("2500 ft", Level::Altitude(2500)),
+            ("1500 ft agl", Level::Height(1500)),
+        ] {
+            assert_eq!(s.parse::<Level>().unwrap(), level);
+        }
+
+        assert_eq!(Level::Height(1500).to_string(), "1500 ft AGL");
+        for s in ["SFC", "UNL", "FL195", "3500 ft", "1000 ft AGL"] {
+            assert_eq!(s.parse::<Level>().unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn level_parse_invalid() {
+        for s in ["", "FL", "FLx", "2500", "2500 m", "ft", "2500 ft MSL"] {
+            assert!(s.parse::<Level>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn level_order() {
+        assert!(Level::Sfc < Level::Altitude(1000));
+        assert!(Level::Altitude(3000) < Level::Fl(30));
+        assert!(Level::Altitude(5000) > Level::Fl(45));
+        assert!(Level::Fl(660) < Level::Unlimited);
+    }
+
+    #[test]
+    fn distance_parse_format() {
+        let distance: Distance = "2.5 nm".parse().unwrap();
+        assert_eq!(distance, Distance::nm(2.5));
+        assert_eq!(distance.metres(), 4630.0);
+        assert_eq!(distance.to_string(), "2.5 nm");
+
+        let distance: Distance = "500 m".parse().unwrap();
+        assert_eq!(distance.kilometres(), 0.5);
+        assert_eq!(distance.to_string(), "500 m");
+
+        assert_eq!("0 nm".parse::<Distance>().unwrap().to_string(), "0 nm");
+    }
+
+    #[test]
+    fn distance_parse_invalid() {
+        for s in ["", "2.5", "nm", "2.5 mi", "-1 nm", "NaN nm", "1 nm x"] {
+            assert!(s.parse::<Distance>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn schema_version() {
+        let data = |version: u64| migrate(&mut json!({"release": {"schema_version": version}}));
+        assert!(data(SCHEMA_VERSION).is_ok());
+        assert!(data(0).is_ok());
+        assert!(data(SCHEMA_VERSION + 1).unwrap_err().contains("newer"));
+        assert!(migrate(&mut json!({})).is_err());
+        assert!(migrate(&mut json!({"release": {"schema_version": "1"}})).is_err());
+    }
+
+    #[test]
+    fn migrate_v0_data() {
+        let yaixm = from_json(include_str!("../tests/data/square_v0.json")).unwrap();
+        assert_eq!(yaixm.release.schema_version, SCHEMA_VERSION);
+        assert_eq!(yaixm.airspace[0].name, "SQUARE");
+        assert!(yaixm.rat.is_empty() && yaixm.loa.is_empty());
+        assert!(yaixm.obstacle.is_empty() && yaixm.service.is_empty());
+    }
 
-    if parts[1] == "nm" {
-        dist * 1852.0
-    } else {
-        dist * 1000.0
+    #[test]
+    fn release_date() {
+        let release = |airac_date: &str| Release {
+            airac_date: airac_date.to_string(),
+            timestamp: String::new(),
+            schema_version: SCHEMA_VERSION,
+            note: String::new(),
+            commit: String::new(),
+        };
+        assert_eq!(release("2025-01-23T00:00:00Z").date(), "2025-01-23");
+        assert_eq!(release("2025").date(), "2025");
+        assert_eq!(release("").date(), "");
     }
 }
@@ -0,0 +1,54 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use asselect::yaixm::{parse, Boundary, DataFormat, IcaoClass, IcaoType, Level};
+
+#[test]
+fn aixm_message() {
+    let text = include_str!("data/aixm.xml");
+    assert_eq!(DataFormat::detect(None, "aixm.xml"), DataFormat::Aixm);
+    let yaixm = parse(text, DataFormat::Aixm).unwrap();
+
+    assert_eq!(yaixm.release.airac_date, "2025-01-23T00:00:00Z");
+    assert_eq!(yaixm.airspace.len(), 2);
+
+    let ctr = &yaixm.airspace[0];
+    assert_eq!(ctr.id.as_deref(), Some("11111111-2222"));
+    assert_eq!(ctr.name, "DUBLIN CTR");
+    assert_eq!(ctr.icao_type, IcaoType::Ctr);
+    assert_eq!(ctr.icao_class, Some(IcaoClass::C));
+    let volume = &ctr.geometry[0];
+    assert_eq!(
+        (volume.lower, volume.upper),
+        (Level::Sfc, Level::Altitude(5000))
+    );
+    assert!(matches!(
+        volume.boundary[..],
+        [Boundary::Line(_), Boundary::Arc(_), Boundary::Line(_)]
+    ));
+    if let Boundary::Arc(arc) = &volume.boundary[1] {
+        assert_eq!(arc.dir, "cw");
+    }
+
+    // Name from designator, metres to feet
+    let danger = &yaixm.airspace[1];
+    assert_eq!(danger.name, "EID1");
+    let volume = &danger.geometry[0];
+    assert_eq!(
+        (volume.lower, volume.upper),
+        (Level::Height(984), Level::Fl(95))
+    );
+    assert!(matches!(volume.boundary[..], [Boundary::Circle(_)]));
+}
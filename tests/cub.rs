@@ -0,0 +1,111 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use asselect::cub::cub;
+use asselect::settings::Settings;
+use asselect::yaixm::from_json;
+
+// No Naviter written reference file is available, so fields are read
+// back at the offsets in Naviter's CUB format description and checked
+// against values worked out by hand from square.json
+
+fn u8_at(buf: &[u8], offset: usize) -> u8 {
+    buf[offset]
+}
+
+fn i16_at(buf: &[u8], offset: usize) -> i16 {
+    i16::from_le_bytes(buf[offset..offset + 2].try_into().unwrap())
+}
+
+fn i32_at(buf: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn f32_at(buf: &[u8], offset: usize) -> f32 {
+    f32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+// Degrees and minutes to radians
+fn rad(deg: f64, min: f64) -> f32 {
+    (deg + min / 60.0).to_radians() as f32
+}
+
+#[test]
+fn cub_square() {
+    let yaixm = from_json(include_str!("data/square.json")).unwrap();
+    let buf = cub(&yaixm, &Settings::default());
+
+    // Header
+    assert_eq!(&buf[0..4], &[0x43, 0x41, 0x5a, 0x42]);
+    assert!(buf[4..116].starts_with(b"UK Airspace AIRAC 2025-01-23"));
+    assert_eq!(u8_at(&buf, 132), 1);
+    let (item_size, point_size) = (i32_at(&buf, 154), i32_at(&buf, 158));
+    assert_eq!((item_size, point_size), (43, 5));
+    assert_eq!(i32_at(&buf, 162), 1);
+    assert_eq!(i32_at(&buf, 166), 4);
+    let bounds = [170, 174, 178, 182].map(|offset| f32_at(&buf, offset));
+    assert_eq!(
+        bounds,
+        [
+            rad(-1.0, 0.0),
+            rad(51.0, 10.0),
+            -rad(0.0, 50.0),
+            rad(51.0, 0.0)
+        ]
+    );
+    let scale = f32_at(&buf, 194);
+    let (items, points) = (i32_at(&buf, 198) as usize, i32_at(&buf, 202) as usize);
+    assert_eq!((items, points), (210, 253));
+    assert_eq!(i32_at(&buf, 206), 0);
+
+    // Item, bounds as header, CTR class D, SFC to 2500 ft (762 m) MSL
+    assert_eq!(
+        [0, 4, 8, 12].map(|offset| f32_at(&buf, items + offset)),
+        bounds
+    );
+    assert_eq!(u8_at(&buf, items + 16), 0x24);
+    assert_eq!(u8_at(&buf, items + 17), 0x21);
+    assert_eq!(i16_at(&buf, items + 18), 0);
+    assert_eq!(i16_at(&buf, items + 20), 762);
+    assert_eq!(i32_at(&buf, items + 22), 0);
+
+    // Corners, relative to bottom left in units of scale
+    let side = |min: f64| (rad(0.0, min) / scale).round() as i16;
+    let corners = (0..4)
+        .map(|n| {
+            let offset = points + n * 5;
+            assert_eq!(u8_at(&buf, offset), 0x01);
+            (i16_at(&buf, offset + 1), i16_at(&buf, offset + 3))
+        })
+        .collect::<Vec<(i16, i16)>>();
+    for corner in [
+        (0, 0),
+        (0, side(10.0)),
+        (side(10.0), side(10.0)),
+        (side(10.0), 0),
+    ] {
+        let found = corners
+            .iter()
+            .any(|c| (c.0 - corner.0).abs() <= 1 && (c.1 - corner.1).abs() <= 1);
+        assert!(found, "corner {:?} not in {:?}", corner, corners);
+    }
+
+    // Name and frequency (Hz) attributes
+    let attrs = &buf[points + 20..];
+    assert_eq!(&attrs[..8], b"\x40\x06SQUARE");
+    assert_eq!(attrs[8], 0x41);
+    assert_eq!(i32_at(attrs, 9), 123_450_000);
+    assert_eq!(&attrs[13..], &[0]);
+}
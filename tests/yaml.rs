@@ -0,0 +1,66 @@
+// Copyright 2024, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use asselect::yaixm::{from_yaml, from_yaml_files, SCHEMA_VERSION};
+
+const AIRSPACE: &str = include_str!("data/yaml/airspace.yaml");
+const RAT: &str = include_str!("data/yaml/rat.yaml");
+const SERVICE: &str = include_str!("data/yaml/service.yaml");
+
+// Single file with all sections
+#[test]
+fn yaml_merged() {
+    let yaixm = from_yaml(include_str!("data/yaml/yaixm.yaml")).unwrap();
+    assert_eq!(yaixm.airspace.len(), 1);
+    assert_eq!(yaixm.rat.len(), 1);
+    assert_eq!(yaixm.service.len(), 1);
+    assert!(yaixm.loa.is_empty());
+    assert!(yaixm.obstacle.is_empty());
+    assert_eq!(yaixm.release.schema_version, SCHEMA_VERSION);
+}
+
+// Separate files, as in the unreleased data repository
+#[test]
+fn yaml_split() {
+    let yaixm = from_yaml_files(&[AIRSPACE, RAT, SERVICE]).unwrap();
+    assert_eq!(yaixm.airspace[0].name, "TEST ATZ");
+    assert_eq!(yaixm.rat[0].name, "TEST RAT");
+    assert_eq!(yaixm.service[0].callsign, "TEST RADIO");
+
+    // Airspace file on its own
+    let yaixm = from_yaml(AIRSPACE).unwrap();
+    assert_eq!(yaixm.airspace.len(), 1);
+    assert!(yaixm.rat.is_empty());
+}
+
+// Files concatenated as a multi-document stream
+#[test]
+fn yaml_documents() {
+    let text = [AIRSPACE, RAT, SERVICE].join("---\n");
+    let yaixm = from_yaml(&text).unwrap();
+    assert_eq!(yaixm.rat.len(), 1);
+    assert_eq!(yaixm.service.len(), 1);
+}
+
+#[test]
+fn yaml_duplicate_airspace() {
+    let yaixm = from_yaml_files(&[AIRSPACE, AIRSPACE]).unwrap();
+    assert_eq!(yaixm.airspace.len(), 2);
+
+    assert!(from_yaml_files(&[AIRSPACE, "release: x"]).is_err());
+    assert!(from_yaml_files(&["release: x", "release: y"])
+        .unwrap_err()
+        .contains("duplicate"));
+}